@@ -1,6 +1,17 @@
 use clap::{Arg, ArgMatches};
-use cryogen_prelude::CompileVariablePlugin;
-use std::{fs::File, io::Read};
+#[cfg(feature = "schema")]
+use cryogen_prelude::schema::Schema;
+use cryogen_prelude::{
+    CompileVariablePlugin, Error, Interpretation, Merge, Selector, Source, VarMapping, Variables,
+};
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{stdin, Read, Write},
+    path::{Component, Path, PathBuf},
+    process,
+};
+use tera::{to_value, Context, Tera, Value};
 
 // Build a vector of plugins to use.
 //
@@ -18,19 +29,138 @@ macro_rules! plugins {
     }
 }
 
+// Invokes a macro with the list of every enabled plugin.
+//
+macro_rules! with_plugins {
+    ($mac:ident) => {
+        $mac! {
+            ::cryogen_plugin_primitives::StringPlugin;
+            ::cryogen_plugin_primitives::FloatPlugin;
+            ::cryogen_plugin_primitives::IntPlugin;
+            ::cryogen_plugin_primitives::BooleanPlugin;
+            #[cfg(feature = "csv")]
+            ::cryogen_plugin_csv::CsvPlugin;
+            #[cfg(feature = "dir")]
+            ::cryogen_plugin_dir::DirPlugin;
+            #[cfg(feature = "env")]
+            ::cryogen_plugin_env::EnvPlugin;
+            #[cfg(feature = "env")]
+            ::cryogen_plugin_env::EnvPrefixPlugin;
+            #[cfg(feature = "front-matter")]
+            ::cryogen_plugin_front_matter::FrontMatterPlugin;
+            #[cfg(feature = "github-markdown")]
+            ::cryogen_plugin_github_markdown::GithubMarkdownPlugin;
+            #[cfg(feature = "json")]
+            ::cryogen_plugin_json::JsonPlugin;
+            #[cfg(feature = "markdown")]
+            ::cryogen_plugin_markdown::MarkdownPlugin;
+            #[cfg(feature = "toml")]
+            ::cryogen_plugin_toml::TomlPlugin;
+            #[cfg(feature = "yaml")]
+            ::cryogen_plugin_yaml::YamlPlugin;
+        }
+    };
+}
+
+/// The path that refers to stdin, instead of a file.
+///
+pub const STDIN: &str = "-";
+
 /// Opens the tera template specified in ArgMatches.
 ///
-pub fn open_template<'a>(args: &'a ArgMatches<'a>) -> (&'a str, String) {
-    let file_path = args.value_of("TEMPLATE").unwrap();
+pub fn open_template<'a>(args: &'a ArgMatches<'a>) -> Result<(&'a str, String), Error> {
+    let file_path = args.value_of("TEMPLATE").ok_or_else(|| {
+        Error::Usage("no template specified on the command line or in the config".into())
+    })?;
+
+    read_template(file_path).map(|buf| (file_path, buf))
+}
+
+/// Reads the contents of a tera template at a path, or from stdin if the
+/// path is `-`.
+///
+pub fn read_template(file_path: &str) -> Result<String, Error> {
+    let mut file: Box<dyn Read> = if file_path == STDIN {
+        Box::new(stdin())
+    } else {
+        File::open(file_path)
+            .map(|file| Box::new(file) as Box<dyn Read>)
+            .map_err(|e| Error::from(e).with_path(file_path))?
+    };
+    let mut buf = String::new();
+
+    file.read_to_string(&mut buf)
+        .map_err(|e| Error::from(e).with_path(file_path))?;
+
+    Ok(buf)
+}
+
+/// Computes the path to write a rendered file to. The path of the source
+/// relative to the root is mirrored in the output directory.
+///
+pub fn output_path(
+    output_dir: &Path,
+    root: Option<&Path>,
+    source: &Path,
+) -> Result<PathBuf, Error> {
+    let relative = match root {
+        Some(root) => source.strip_prefix(root).map_err(|_| {
+            Error::Usage(format!(
+                "({}) is not under the root directory ({})",
+                source.display(),
+                root.display()
+            ))
+        })?,
+        None => source,
+    };
+    let mirrored: PathBuf = relative
+        .components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .collect();
+
+    Ok(output_dir.join(mirrored))
+}
 
-    match File::open(file_path) {
-        Ok(mut file) => {
-            let mut buf = String::new();
-            let _ = file.read_to_string(&mut buf);
-            (file_path, buf)
+/// Writes a rendered file, creating any missing parent directories.
+///
+/// The contents are written to a temporary file in the same directory, and
+/// then renamed into place, so a failed write never leaves a truncated file
+/// behind. If the file already has the same contents, it is left untouched
+/// to avoid updating its modification time.
+///
+pub fn write_output(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    if let Ok(existing) = fs::read(path) {
+        if existing == contents {
+            return Ok(());
         }
-        Err(e) => panic!(format!("failed to open template ({}): {:?}", file_path, e)),
     }
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::Usage(format!("invalid output path ({})", path.display())))?;
+    let mut tmp_name = OsString::from(".");
+
+    tmp_name.push(file_name);
+    tmp_name.push(format!(".{}.tmp", process::id()));
+
+    let tmp_path = path.with_file_name(tmp_name);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| Error::from(e).with_path(parent.to_string_lossy()))?;
+    }
+
+    File::create(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp_path, path))
+        .map_err(|e| {
+            let _ = fs::remove_file(&tmp_path);
+
+            Error::from(e).with_path(path.to_string_lossy())
+        })
 }
 
 /// Registers a plugin.
@@ -49,3 +179,553 @@ where
     );
     plugins.extend(T::additional_args());
 }
+
+/// The name of the argument that chooses how variables bound more than once
+/// are merged.
+///
+const MERGE: &str = "merge";
+
+/// The name of the argument that reports where each variable came from.
+///
+const MERGE_REPORT: &str = "merge-report";
+
+/// The name of the argument that binds a variable with any plugin.
+///
+const BIND: &str = "bind";
+
+/// The name of the argument that validates a variable against a JSON Schema.
+///
+#[cfg(feature = "schema")]
+const SCHEMA: &str = "schema";
+
+/// A value read by a plugin, which is bound to its variable once every
+/// plugin has been executed.
+///
+pub struct Binding {
+    index: usize,
+    var_path: Vec<String>,
+    source: String,
+    value: Value,
+    #[cfg(feature = "schema")]
+    schema_pointer: &'static str,
+    #[cfg(feature = "schema")]
+    schema_requires: Option<&'static str>,
+}
+
+/// Returns the mappings for a plugin, along with their positions on the
+/// command line. Mappings come from the plugin's own argument, and from the
+/// bind arguments that name the plugin.
+///
+pub fn plugin_mappings<'a, 'b>(
+    args: &'a ArgMatches<'b>,
+    plugin: &str,
+) -> Result<Vec<(usize, VarMapping<'a>)>, Error> {
+    let mut mappings = Vec::new();
+
+    let values = args.values_of(plugin).into_iter().flatten();
+    let indices = args.indices_of(plugin).into_iter().flatten();
+    let default_name = plugin_default_var_name(plugin);
+    for (value, index) in values.zip(indices) {
+        let mapping = match default_name {
+            Some(name) => VarMapping::with_default_name(value, name)?,
+            None => VarMapping::from_str(value)?,
+        };
+
+        mappings.push((index, mapping));
+    }
+
+    let values = args.values_of(BIND).into_iter().flatten();
+    let indices = args.indices_of(BIND).into_iter().flatten();
+    for (value, index) in values.zip(indices) {
+        let (name, mapping) = VarMapping::from_bind(value)?;
+
+        if name == plugin {
+            mappings.push((index, mapping));
+        }
+    }
+
+    mappings.sort_by_key(|&(index, _)| index);
+
+    Ok(mappings)
+}
+
+/// Checks that every bind argument names an enabled plugin.
+///
+fn check_binds<'a>(args: &'a ArgMatches<'a>) -> Result<(), Error> {
+    let names = plugin_arg_names();
+
+    for value in args.values_of(BIND).into_iter().flatten() {
+        let (name, _) = VarMapping::from_bind(value)?;
+
+        if !names.contains(&name) {
+            return Err(Error::Mapping(format!(
+                "Unknown plugin ({}) in ({}), expected one of: {}",
+                name,
+                value,
+                names.join(", ")
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Splits the path a plugin reads from the selector that follows it, if the
+/// plugin supports selectors.
+///
+fn split_selector<T>(arg_value: &str) -> Result<(&str, Option<Selector>), Error>
+where
+    T: CompileVariablePlugin,
+{
+    if T::SELECTORS {
+        Selector::split(arg_value)
+    } else {
+        Ok((arg_value, None))
+    }
+}
+
+/// Executes a plugin, collecting the values it reads along with the position
+/// of their mappings on the command line.
+///
+pub fn exec_plugin<'a, T>(
+    args: &'a ArgMatches<'a>,
+    bindings: &mut Vec<Binding>,
+) -> Result<(), Error>
+where
+    T: CompileVariablePlugin,
+{
+    let plugin = T::from_args(args);
+
+    for (index, mapping) in plugin_mappings(args, T::ARG_NAME)? {
+        let (path, selector) = split_selector::<T>(mapping.arg_value())?;
+        let src: Source<Box<dyn Read>> = match T::ARG_INTERPRETATION {
+            Interpretation::Raw => Source::Raw(mapping.arg_value(), 0),
+            Interpretation::Path if path == STDIN => Source::File(Box::new(stdin())),
+            Interpretation::Path => {
+                let file = File::open(path).map_err(|e| Error::from(e).with_path(path))?;
+
+                Source::File(Box::new(file))
+            }
+        };
+        let (value, source) = match T::ARG_INTERPRETATION {
+            Interpretation::Raw => (plugin.read(src)?, format!("--{}", T::ARG_NAME)),
+            Interpretation::Path => (
+                plugin.read(src).map_err(|e| e.with_path(path))?,
+                mapping.arg_value().to_string(),
+            ),
+        };
+
+        let value = to_value(&value).map_err(|e| {
+            Error::Mapping(format!("Failed to bind ({}): {}", mapping.var_name(), e))
+        })?;
+        let value = match selector {
+            Some(selector) => selector.select(path, value)?,
+            None => value,
+        };
+
+        bindings.push(Binding {
+            index,
+            var_path: mapping.var_path().to_vec(),
+            source,
+            value,
+            #[cfg(feature = "schema")]
+            schema_pointer: T::SCHEMA_POINTER,
+            #[cfg(feature = "schema")]
+            schema_requires: T::SCHEMA_REQUIRES,
+        });
+    }
+
+    Ok(())
+}
+
+/// Returns the arguments for every enabled plugin, and the arguments that
+/// control how their variables are merged.
+///
+pub fn plugin_args() -> Vec<Arg<'static, 'static>> {
+    let mut args = with_plugins!(plugins);
+
+    args.push(
+        Arg::with_name(BIND)
+            .long(BIND)
+            .help(
+                "Bind a variable with any plugin, as <var_name>=<plugin>:<value> \
+                 (e.g. 'site.nav=yaml:nav.yml')",
+            )
+            .takes_value(true)
+            .multiple(true),
+    );
+    args.push(
+        Arg::with_name(MERGE)
+            .long(MERGE)
+            .help(
+                "How to merge values bound to the same variable more than once. Maps are always \
+                 merged, and other values are an error, or are layered in command line order \
+                 with arrays replaced or appended",
+            )
+            .takes_value(true)
+            .possible_values(&["error", "replace", "append"])
+            .default_value("error"),
+    );
+    args.push(
+        Arg::with_name(MERGE_REPORT)
+            .long(MERGE_REPORT)
+            .help("Print the file each variable was read from to stderr"),
+    );
+
+    #[cfg(feature = "schema")]
+    args.push(
+        Arg::with_name(SCHEMA)
+            .long(SCHEMA)
+            .help(
+                "Validate the values bound to a variable against a JSON Schema file before \
+                 rendering, as <var_name>:<schema> (e.g. 'post:post.schema.json'). Only the \
+                 front matter of Markdown is validated",
+            )
+            .takes_value(true)
+            .multiple(true),
+    );
+
+    args
+}
+
+/// Returns the argument names that bind variables for every enabled plugin.
+///
+pub fn plugin_arg_names() -> Vec<&'static str> {
+    macro_rules! arg_names {
+        ( $( $(#[$feature:meta])* $plug:ty );*; ) => {
+            [
+                $(
+                    $(#[$feature])*
+                    <$plug as CompileVariablePlugin>::ARG_NAME,
+                )*
+            ]
+        }
+    }
+
+    with_plugins!(arg_names).to_vec()
+}
+
+/// Returns the variable a plugin binds when its mappings have no variable
+/// name, if it has one.
+///
+fn plugin_default_var_name(plugin: &str) -> Option<&'static str> {
+    macro_rules! default_names {
+        ( $( $(#[$feature:meta])* $plug:ty );*; ) => {
+            [
+                $(
+                    $(#[$feature])*
+                    (
+                        <$plug as CompileVariablePlugin>::ARG_NAME,
+                        <$plug as CompileVariablePlugin>::DEFAULT_VAR_NAME,
+                    ),
+                )*
+            ]
+        }
+    }
+
+    with_plugins!(default_names)
+        .iter()
+        .find(|&&(name, _)| name == plugin)
+        .and_then(|&(_, var_name)| var_name)
+}
+
+/// Returns the paths of every file read by plugins that interpret their
+/// arguments as paths, and of every schema. Paths that refer to stdin are
+/// included.
+///
+pub fn plugin_paths<'a>(args: &'a ArgMatches<'a>) -> Result<Vec<PathBuf>, Error> {
+    let mut paths = Vec::new();
+
+    macro_rules! paths {
+        ( $( $(#[$feature:meta])* $plug:ty );*; ) => {
+            $(
+                $(#[$feature])*
+                {
+                    if let Interpretation::Path = <$plug as CompileVariablePlugin>::ARG_INTERPRETATION {
+                        let mappings = plugin_mappings(args, <$plug as CompileVariablePlugin>::ARG_NAME)?;
+
+                        for (_, mapping) in mappings {
+                            let (path, _) = split_selector::<$plug>(mapping.arg_value())?;
+
+                            paths.push(PathBuf::from(path));
+                        }
+                    }
+                }
+            )*
+        }
+    }
+
+    with_plugins!(paths);
+
+    #[cfg(feature = "schema")]
+    for value in args.values_of(SCHEMA).into_iter().flatten() {
+        paths.push(PathBuf::from(VarMapping::from_str(value)?.arg_value()));
+    }
+
+    Ok(paths)
+}
+
+/// Checks that stdin is read by at most one plugin mapping or template, and
+/// returns whether it is read at all.
+///
+pub fn uses_stdin<'a>(args: &'a ArgMatches<'a>) -> Result<bool, Error> {
+    let mut claims = plugin_paths(args)?
+        .iter()
+        .filter(|path| *path == Path::new(STDIN))
+        .count();
+
+    if args.value_of("TEMPLATE") == Some(STDIN) {
+        claims += 1;
+    }
+
+    if claims > 1 {
+        return Err(Error::Usage(format!(
+            "stdin ({}) can only be read once, but is used {} times",
+            STDIN, claims
+        )));
+    }
+
+    Ok(claims == 1)
+}
+
+/// Validates the values bound to each variable that has a schema, reporting
+/// the violations of every schema before any of them are bound.
+///
+#[cfg(feature = "schema")]
+fn validate<'a>(args: &'a ArgMatches<'a>, bindings: &[Binding]) -> Result<(), Error> {
+    let null = Value::Null;
+    let mut schemas = Vec::new();
+    let mut violations = Vec::new();
+
+    for value in args.values_of(SCHEMA).into_iter().flatten() {
+        let mapping = VarMapping::from_str(value)?;
+        let schema = Schema::read(mapping.arg_value())?;
+        let bound: Vec<&Binding> = bindings
+            .iter()
+            .filter(|binding| binding.var_path[..] == *mapping.var_path())
+            .collect();
+
+        for binding in &bound {
+            match binding.schema_requires {
+                Some(required) if !args.is_present(required) => {
+                    return Err(Error::Usage(format!(
+                        "schema ({}) validates the front matter of ({}), which is only read \
+                         with --{}",
+                        mapping.arg_value(),
+                        binding.source,
+                        required
+                    )))
+                }
+                _ => (),
+            }
+        }
+
+        let values: Vec<(&str, &Value)> = bound
+            .iter()
+            .map(|binding| {
+                let value = binding.value.pointer(binding.schema_pointer);
+
+                (binding.source.as_str(), value.unwrap_or(&null))
+            })
+            .collect();
+
+        if values.is_empty() {
+            return Err(Error::Mapping(format!(
+                "Schema ({}) is for ({}), but no plugin binds it",
+                mapping.arg_value(),
+                mapping.var_name()
+            )));
+        }
+
+        let found = schema.violations(values);
+
+        if !found.is_empty() {
+            schemas.push(schema.path().to_string());
+            violations.extend(found);
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Schema {
+            schemas,
+            violations,
+        })
+    }
+}
+
+/// Executes every enabled plugin, binding the variables they produce in
+/// command line order, and adds them to the tera context. Returns the
+/// source of every variable if a merge report was requested, so it can be
+/// printed once (see `print_report`), rather than on every render.
+///
+pub fn exec_plugins<'a>(
+    args: &'a ArgMatches<'a>,
+    template_vars: &mut Context,
+) -> Result<Option<String>, Error> {
+    let mut bindings = Vec::new();
+    let mut vars = Variables::with_merge(match args.value_of(MERGE) {
+        Some("replace") => Merge::Replace,
+        Some("append") => Merge::Append,
+        _ => Merge::Error,
+    })
+    .with_collision_hint(format!("use --{} to layer them", MERGE));
+
+    check_binds(args)?;
+    uses_stdin(args)?;
+
+    macro_rules! exec {
+        ( $( $(#[$feature:meta])* $plug:ty );*; ) => {
+            $(
+                $(#[$feature])*
+                exec_plugin::<$plug>(args, &mut bindings)?;
+            )*
+        }
+    }
+
+    with_plugins!(exec);
+
+    bindings.sort_by_key(|binding| binding.index);
+
+    #[cfg(feature = "schema")]
+    validate(args, &bindings)?;
+
+    for binding in bindings {
+        vars.bind(&binding.var_path, &binding.source, binding.value)?;
+    }
+
+    vars.extend_context(template_vars);
+
+    if !args.is_present(MERGE_REPORT) {
+        return Ok(None);
+    }
+
+    let width = vars.origins().keys().map(String::len).max().unwrap_or(0);
+    let report = vars
+        .origins()
+        .iter()
+        .map(|(name, source)| format!("{:width$}  {}\n", name, source, width = width))
+        .collect();
+
+    Ok(Some(report))
+}
+
+/// Prints a merge report to stderr, unless it's the same as the last report
+/// that was printed. Watching and serving render again and again, but the
+/// report is only printed when the sources of the variables change.
+///
+pub fn print_report(report: Option<String>, last: &mut Option<String>) {
+    if let Some(report) = report {
+        if last.as_ref() != Some(&report) {
+            eprint!("{}", report);
+            *last = Some(report);
+        }
+    }
+}
+
+/// Lets every enabled plugin register the filters, testers, and functions
+/// that templates can use.
+///
+pub fn extend_tera<'a>(args: &'a ArgMatches<'a>, tera: &mut Tera) {
+    macro_rules! extend {
+        ( $( $(#[$feature:meta])* $plug:ty );*; ) => {
+            $(
+                $(#[$feature])*
+                <$plug as CompileVariablePlugin>::from_args(args).extend_tera(tera);
+            )*
+        }
+    }
+
+    with_plugins!(extend);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_output;
+    use std::{
+        env,
+        fs::{self, File},
+        path::PathBuf,
+        process,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    /// Creates an empty directory for a test to write to.
+    ///
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("cryogen-{}-{}", name, process::id()));
+
+        let _ = fs::remove_dir_all(&dir);
+
+        dir
+    }
+
+    #[test]
+    fn test_write_output_creates_parents() {
+        let dir = temp_dir("write-parents");
+        let path = dir.join("a/b/index.html");
+
+        write_output(&path, b"<p>a</p>").unwrap();
+
+        let contents = fs::read(&path);
+        let entries: Vec<_> = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(contents.unwrap(), b"<p>a</p>");
+        assert_eq!(entries, vec!["index.html"]);
+    }
+
+    #[test]
+    fn test_write_output_skips_unchanged_contents() {
+        let dir = temp_dir("write-unchanged");
+        let path = dir.join("index.html");
+        let past = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let modified = || fs::metadata(&path).unwrap().modified().unwrap();
+
+        write_output(&path, b"<p>a</p>").unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(past))
+            .unwrap();
+
+        write_output(&path, b"<p>a</p>").unwrap();
+        let unchanged = modified();
+
+        write_output(&path, b"<p>b</p>").unwrap();
+        let changed = modified();
+        let contents = fs::read(&path);
+        let entries = fs::read_dir(&dir).unwrap().count();
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(unchanged, past);
+        assert!(changed > past);
+        assert_eq!(contents.unwrap(), b"<p>b</p>");
+        assert_eq!(entries, 1);
+    }
+
+    #[test]
+    fn test_write_output_removes_temporary_file_on_failure() {
+        let dir = temp_dir("write-failure");
+        let path = dir.join("index.html");
+
+        fs::create_dir_all(path.join("nested")).unwrap();
+
+        let result = write_output(&path, b"<p>a</p>");
+        let entries: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(result.is_err());
+        assert_eq!(entries, vec!["index.html"]);
+    }
+}
@@ -0,0 +1,231 @@
+use app::{exec_plugins, output_path, plugin_args, print_report, read_template, write_output};
+use clap::{App, Arg, ArgMatches, SubCommand};
+#[cfg(feature = "github-markdown")]
+use cryogen_plugin_github_markdown::GithubMarkdownPlugin;
+#[cfg(feature = "markdown")]
+use cryogen_plugin_markdown::MarkdownPlugin;
+use cryogen_prelude::{markdown::RenderedMarkdown, CompileVariablePlugin, Error, Source};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+use templates::{templates_args, Templates};
+use tera::Context;
+
+const OUTPUT_DIR: &str = "output-dir";
+const TEMPLATE_DIR: &str = "template-dir";
+const DEFAULT_TEMPLATE: &str = "default-template";
+const RENDERER: &str = "renderer";
+const PAGE_VAR: &str = "page-var";
+const EXTENSION: &str = "extension";
+
+/// The metadata key used to pick a template for a content file.
+///
+const TEMPLATE_KEY: &str = "template";
+
+/// The plugin used to render content files.
+///
+enum Renderer {
+    #[cfg(feature = "markdown")]
+    Markdown(MarkdownPlugin),
+    #[cfg(feature = "github-markdown")]
+    GithubMarkdown(GithubMarkdownPlugin),
+}
+
+impl Renderer {
+    #[cfg(feature = "markdown")]
+    const DEFAULT: &'static str = "markdown";
+
+    #[cfg(not(feature = "markdown"))]
+    const DEFAULT: &'static str = "gfm";
+
+    const POSSIBLE_VALUES: &'static [&'static str] = &[
+        #[cfg(feature = "markdown")]
+        "markdown",
+        #[cfg(feature = "github-markdown")]
+        "gfm",
+    ];
+
+    /// Builds the renderer named in the arguments. Metadata blocks are always
+    /// read, so they can be used to pick a template.
+    ///
+    fn from_args<'a>(args: &'a ArgMatches<'a>) -> Renderer {
+        match args.value_of(RENDERER).unwrap_or(Self::DEFAULT) {
+            #[cfg(feature = "github-markdown")]
+            "gfm" => {
+                Renderer::GithubMarkdown(GithubMarkdownPlugin::from_args(args).yaml_metadata(true))
+            }
+            #[cfg(feature = "markdown")]
+            _ => Renderer::Markdown(MarkdownPlugin::from_args(args).yaml_metadata(true)),
+            #[cfg(not(feature = "markdown"))]
+            _ => unreachable!(),
+        }
+    }
+
+    fn read(&self, path: &Path) -> Result<RenderedMarkdown, Error> {
+        let file =
+            File::open(path).map_err(|e| Error::from(e).with_path(path.to_string_lossy()))?;
+        let src: Source<File> = Source::File(file);
+
+        let page = match *self {
+            #[cfg(feature = "markdown")]
+            Renderer::Markdown(ref plugin) => plugin.read(src),
+            #[cfg(feature = "github-markdown")]
+            Renderer::GithubMarkdown(ref plugin) => plugin.read(src),
+        };
+
+        page.map_err(|e| e.with_path(path.to_string_lossy()))
+    }
+}
+
+/// Recursively collects every file with a given extension in a directory.
+///
+fn collect_content(dir: &Path, extension: &str, content: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let entries = fs::read_dir(dir).map_err(|e| Error::from(e).with_path(dir.to_string_lossy()))?;
+
+    for entry in entries {
+        let path = entry
+            .map_err(|e| Error::from(e).with_path(dir.to_string_lossy()))?
+            .path();
+
+        if path.is_dir() {
+            collect_content(&path, extension, content)?;
+        } else if path.extension().is_some_and(|ext| ext == extension) {
+            content.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Command to render a directory of Markdown content through templates.
+///
+pub struct Command;
+
+impl Command {
+    pub const COMMAND_NAME: &'static str = "build";
+
+    pub const POSITIONAL: &'static str = "CONTENT";
+
+    pub fn app<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name(Self::COMMAND_NAME)
+            .about("Renders every Markdown file in a content directory through a template")
+            .arg(
+                Arg::with_name(Self::POSITIONAL)
+                    .help("The directory of Markdown content to render")
+                    .index(1),
+            )
+            .arg(
+                Arg::with_name(OUTPUT_DIR)
+                    .long(OUTPUT_DIR)
+                    .short("o")
+                    .help("The directory to write rendered pages to")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(TEMPLATE_DIR)
+                    .long(TEMPLATE_DIR)
+                    .help("The directory templates are looked up in")
+                    .takes_value(true)
+                    .default_value("."),
+            )
+            .arg(
+                Arg::with_name(DEFAULT_TEMPLATE)
+                    .long(DEFAULT_TEMPLATE)
+                    .help("The template used for content without a `template` metadata key")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(RENDERER)
+                    .long(RENDERER)
+                    .help("The plugin used to render content")
+                    .takes_value(true)
+                    .possible_values(Renderer::POSSIBLE_VALUES)
+                    .default_value(Renderer::DEFAULT),
+            )
+            .arg(
+                Arg::with_name(PAGE_VAR)
+                    .long(PAGE_VAR)
+                    .help("The variable the rendered content is assigned to")
+                    .takes_value(true)
+                    .default_value("page"),
+            )
+            .arg(
+                Arg::with_name(EXTENSION)
+                    .long(EXTENSION)
+                    .help("The extension of content files")
+                    .takes_value(true)
+                    .default_value("md"),
+            )
+            .args(&templates_args())
+            .args(&plugin_args())
+    }
+
+    pub fn exec<'a>(args: &'a ArgMatches<'a>) -> Result<(), Error> {
+        let content_dir = args
+            .value_of(Self::POSITIONAL)
+            .map(Path::new)
+            .ok_or_else(|| {
+                Error::Usage(
+                    "no content directory specified on the command line or in the config".into(),
+                )
+            })?;
+        let output_dir = args.value_of(OUTPUT_DIR).map(Path::new).ok_or_else(|| {
+            Error::Usage(
+                "no output directory specified on the command line or in the config".into(),
+            )
+        })?;
+        let template_dir = Path::new(args.value_of(TEMPLATE_DIR).unwrap());
+        let default_template = args.value_of(DEFAULT_TEMPLATE);
+        let page_var = args.value_of(PAGE_VAR).unwrap();
+        let renderer = Renderer::from_args(args);
+        let mut content = Vec::new();
+        let mut tera = Templates::from_args(args)?;
+        let mut template_contents = HashMap::new();
+        let mut template_vars = Context::new();
+
+        collect_content(content_dir, args.value_of(EXTENSION).unwrap(), &mut content)?;
+        content.sort();
+
+        print_report(exec_plugins(args, &mut template_vars)?, &mut None);
+
+        for path in content {
+            let page = renderer.read(&path)?;
+            let template = page
+                .metadata()
+                .and_then(|meta| meta.get(TEMPLATE_KEY))
+                .and_then(|template| template.as_str())
+                .or(default_template)
+                .ok_or_else(|| {
+                    Error::Usage(format!(
+                        "no template specified for content ({})",
+                        path.display()
+                    ))
+                })?;
+            let template_path = template_dir.join(template);
+            let template_path = template_path.to_string_lossy().into_owned();
+
+            if !template_contents.contains_key(&template_path) {
+                let contents = read_template(&template_path)?;
+
+                template_contents.insert(template_path.clone(), contents);
+            }
+
+            let mut page_vars = template_vars.clone();
+
+            page_vars.insert(page_var, &page);
+
+            let rendered = tera.render(
+                &template_path,
+                &template_contents[&template_path],
+                &page_vars,
+            )?;
+            let out = output_path(output_dir, Some(content_dir), &path)?.with_extension("html");
+
+            write_output(&out, rendered.as_ref())?;
+        }
+
+        Ok(())
+    }
+}
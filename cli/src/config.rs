@@ -0,0 +1,457 @@
+use app::{plugin_arg_names, plugin_mappings};
+#[cfg(any(feature = "markdown", feature = "github-markdown"))]
+use build;
+use clap::ArgMatches;
+use cryogen_prelude::{Error, VarMapping};
+use diagnostic::COLOR;
+use multi;
+use serve;
+use single;
+use std::{
+    collections::HashSet,
+    ffi::{OsStr, OsString},
+    fs::File,
+    io::Read,
+    path::Path,
+};
+use toml::{value::Table, Value};
+
+/// The name of the argument to supply a config file path.
+///
+pub const CONFIG: &str = "config";
+
+/// The config file that is loaded if one isn't explicitly supplied.
+///
+const DEFAULT_CONFIG: &str = "cryogen.toml";
+
+/// Section containing arguments passed to every subcommand.
+///
+const OPTIONS: &str = "options";
+
+/// Section containing variable bindings.
+///
+const VARS: &str = "vars";
+
+/// Returns the name of the positional argument of a subcommand.
+///
+fn positional(subcommand: &str) -> Option<&'static str> {
+    match subcommand {
+        single::Command::COMMAND_NAME => Some(single::Command::POSITIONAL),
+        multi::Command::COMMAND_NAME => Some(multi::Command::POSITIONAL),
+        serve::Command::COMMAND_NAME => Some(serve::Command::POSITIONAL),
+        #[cfg(any(feature = "markdown", feature = "github-markdown"))]
+        build::Command::COMMAND_NAME => Some(build::Command::POSITIONAL),
+        _ => None,
+    }
+}
+
+/// Returns whether a token before the subcommand is a global option that
+/// takes its value from the next token (e.g. `--color never`, but not
+/// `--color=never`).
+///
+fn takes_value(token: &OsStr) -> bool {
+    match token.to_str() {
+        Some("-c") => true,
+        Some(token) if token.starts_with("--") => [CONFIG, COLOR].contains(&&token[2..]),
+        _ => false,
+    }
+}
+
+/// Finds the subcommand in a command line, skipping the values of global
+/// options.
+///
+fn subcommand_index(argv: &[OsString], subcommand: &str) -> Option<usize> {
+    let mut index = 1;
+
+    while index < argv.len() {
+        if argv[index] == *subcommand {
+            return Some(index);
+        }
+
+        if takes_value(&argv[index]) {
+            index += 1;
+        }
+
+        index += 1;
+    }
+
+    None
+}
+
+/// Converts a scalar config value into an argument value.
+///
+fn scalar(key: &str, value: &Value) -> Result<String, Error> {
+    match *value {
+        Value::String(ref s) => Ok(s.clone()),
+        Value::Integer(i) => Ok(i.to_string()),
+        Value::Float(f) => Ok(f.to_string()),
+        Value::Boolean(b) => Ok(b.to_string()),
+        Value::Datetime(ref d) => Ok(d.to_string()),
+        Value::Array(_) | Value::Table(_) => Err(Error::Usage(format!(
+            "expected a single value for ({}) in config",
+            key
+        ))),
+    }
+}
+
+/// Returns every value of a config entry, treating single values as a list
+/// of one.
+///
+fn values<'a>(key: &str, value: &'a Value) -> Result<Vec<&'a Value>, Error> {
+    match *value {
+        Value::Array(ref values) => Ok(values.iter().collect()),
+        Value::Table(_) => Err(Error::Usage(format!(
+            "unexpected table for ({}) in config",
+            key
+        ))),
+        ref value => Ok(vec![value]),
+    }
+}
+
+/// A project configuration file that supplies arguments for the command line.
+///
+/// ```toml
+/// [options]
+/// markdown-tables = true
+///
+/// [vars]
+/// site = { json = "site.json" }
+///
+/// [single]
+/// template = "page.html"
+/// ```
+///
+/// Arguments supplied on the command line always take precedence over
+/// values in the config.
+///
+pub struct Config {
+    table: Table,
+}
+
+impl Config {
+    /// Loads the config file named in the arguments, or the default config
+    /// file if it exists.
+    ///
+    pub fn load<'a>(args: &ArgMatches<'a>) -> Result<Option<Config>, Error> {
+        let path = match args.value_of(CONFIG) {
+            Some(path) => path,
+            None if Path::new(DEFAULT_CONFIG).is_file() => DEFAULT_CONFIG,
+            None => return Ok(None),
+        };
+        let mut buf = String::new();
+
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut buf))
+            .map_err(|e| Error::from(e).with_path(path))?;
+
+        match buf.parse::<Value>() {
+            Ok(Value::Table(table)) => Ok(Some(Config { table })),
+            Ok(_) => Err(Error::Usage(format!(
+                "expected a table in config ({})",
+                path
+            ))),
+            Err(e) => Err(Error::Usage(format!(
+                "failed to parse config ({}): {}",
+                path, e
+            ))),
+        }
+    }
+
+    /// Builds a new command line by inserting arguments from the config
+    /// after the subcommand. Arguments already present in the parsed command
+    /// line are skipped.
+    ///
+    pub fn merge<'a>(
+        &self,
+        args: &ArgMatches<'a>,
+        argv: Vec<OsString>,
+    ) -> Result<Vec<OsString>, Error> {
+        let (subcommand, sub_args) = match args.subcommand() {
+            (name, Some(sub_args)) => (name, sub_args),
+            _ => return Ok(argv),
+        };
+        let mut tokens = Vec::new();
+
+        if let Some(options) = self.table.get(OPTIONS) {
+            push_args(OPTIONS, options, sub_args, None, &mut tokens)?;
+        }
+
+        if let Some(section) = self.table.get(subcommand) {
+            push_args(
+                subcommand,
+                section,
+                sub_args,
+                positional(subcommand),
+                &mut tokens,
+            )?;
+        }
+
+        if let Some(vars) = self.table.get(VARS) {
+            push_vars(vars, sub_args, &mut tokens)?;
+        }
+
+        let index = subcommand_index(&argv, subcommand).ok_or_else(|| {
+            Error::Usage(format!(
+                "expected the subcommand ({}) on the command line, to add arguments from the \
+                 config after it",
+                subcommand
+            ))
+        })?;
+        let mut merged = argv[..index + 1].to_vec();
+
+        merged.extend(tokens);
+        merged.extend_from_slice(&argv[index + 1..]);
+
+        Ok(merged)
+    }
+}
+
+/// Converts a section of the config into arguments.
+///
+fn push_args<'a>(
+    section: &str,
+    value: &Value,
+    args: &ArgMatches<'a>,
+    positional: Option<&str>,
+    tokens: &mut Vec<OsString>,
+) -> Result<(), Error> {
+    let table = value.as_table().ok_or_else(|| {
+        Error::Usage(format!(
+            "expected config section ({}) to be a table",
+            section
+        ))
+    })?;
+
+    for (key, value) in table {
+        if key == VARS {
+            push_vars(value, args, tokens)?;
+
+            continue;
+        }
+
+        match positional {
+            Some(name) if key.eq_ignore_ascii_case(name) => {
+                if args.occurrences_of(name) == 0 {
+                    for value in values(key, value)? {
+                        tokens.push(scalar(key, value)?.into());
+                    }
+                }
+
+                continue;
+            }
+            _ => (),
+        }
+
+        if args.occurrences_of(key) > 0 {
+            continue;
+        }
+
+        for value in values(key, value)? {
+            match *value {
+                Value::Boolean(true) => tokens.push(format!("--{}", key).into()),
+                Value::Boolean(false) => (),
+                ref value => tokens.push(format!("--{}={}", key, scalar(key, value)?).into()),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts variable bindings in the config into plugin arguments. Keys
+/// are dotted variable names (e.g. `"site.nav"`), and tables of bindings
+/// (e.g. `[vars.site]`) bind nested variables. Each segment is escaped for
+/// the mapping grammar, so it's taken as is. Variables that are bound on the
+/// command line are skipped.
+///
+fn push_vars<'a>(
+    value: &Value,
+    args: &ArgMatches<'a>,
+    tokens: &mut Vec<OsString>,
+) -> Result<(), Error> {
+    let table = value
+        .as_table()
+        .ok_or_else(|| Error::Usage(format!("expected config section ({}) to be a table", VARS)))?;
+    let mut bound = HashSet::new();
+
+    for name in plugin_arg_names() {
+        for (_, mapping) in plugin_mappings(args, name)? {
+            bound.insert(mapping.var_path().to_vec());
+        }
+    }
+
+    push_bindings(table, &mut Vec::new(), &bound, tokens)
+}
+
+/// Converts a table of variable bindings, nested under a variable path.
+///
+fn push_bindings(
+    table: &Table,
+    var_path: &mut Vec<String>,
+    bound: &HashSet<Vec<String>>,
+    tokens: &mut Vec<OsString>,
+) -> Result<(), Error> {
+    for (key, binding) in table {
+        let depth = var_path.len();
+
+        var_path.extend(key.split('.').map(str::to_string));
+
+        let var_name = var_path.join(".");
+        let binding = binding.as_table().ok_or_else(|| {
+            Error::Usage(format!("expected binding for ({}) to be a table", var_name))
+        })?;
+
+        if !binding.is_empty() && binding.values().all(Value::is_table) {
+            push_bindings(binding, var_path, bound, tokens)?;
+        } else if !bound.contains(var_path) {
+            let mut entries = binding.iter();
+
+            match (entries.next(), entries.next()) {
+                (Some((plugin, value)), None) => {
+                    let value = scalar(&var_name, value)?;
+                    let escaped: Vec<String> =
+                        var_path.iter().map(|s| VarMapping::escape(s)).collect();
+
+                    tokens.push(format!("--{}={}:{}", plugin, escaped.join("."), value).into());
+                }
+                _ => {
+                    return Err(Error::Usage(format!(
+                        "expected binding for ({}) to name exactly one plugin",
+                        var_name
+                    )))
+                }
+            }
+        }
+
+        var_path.truncate(depth);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{subcommand_index, Config};
+    use std::ffi::OsString;
+    use toml::Value;
+
+    fn merge(config: &str, argv: &[&str]) -> Vec<OsString> {
+        let config = match config.parse::<Value>().unwrap() {
+            Value::Table(table) => Config { table },
+            _ => unreachable!(),
+        };
+        let argv: Vec<OsString> = argv.iter().map(OsString::from).collect();
+        let args = ::app().get_matches_from(argv.clone());
+
+        config.merge(&args, argv).unwrap()
+    }
+
+    #[test]
+    fn test_merge_after_subcommand() {
+        assert_eq!(
+            merge(
+                "[options]\nmarkdown-tables = true\n[single]\ntemplate = \"page.html\"",
+                &["cryogen", "single", "--json", "a:a.json"],
+            ),
+            vec![
+                "cryogen",
+                "single",
+                "--markdown-tables",
+                "page.html",
+                "--json",
+                "a:a.json",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_skips_command_line_args() {
+        assert_eq!(
+            merge(
+                "[single]\ntemplate = \"page.html\"\nmarkdown-tables = true",
+                &["cryogen", "single", "other.html", "--markdown-tables"],
+            ),
+            vec!["cryogen", "single", "other.html", "--markdown-tables"]
+        );
+    }
+
+    #[test]
+    fn test_merge_escapes_var_names() {
+        assert_eq!(
+            merge(
+                "[vars]\n\"a:b\" = { json = \"a.json\" }\ntitle = { json = \"t.json\" }",
+                &["cryogen", "single", "--json", "title:other.json"],
+            ),
+            vec![
+                "cryogen",
+                "single",
+                "--json=a\\:b:a.json",
+                "--json",
+                "title:other.json",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_nested_vars() {
+        let expected = vec!["cryogen", "single", "--json=site.nav:nav.json"];
+
+        assert_eq!(
+            merge(
+                "[vars]\n\"site.nav\" = { json = \"nav.json\" }",
+                &["cryogen", "single"],
+            ),
+            expected
+        );
+        assert_eq!(
+            merge(
+                "[vars.site]\nnav = { json = \"nav.json\" }",
+                &["cryogen", "single"],
+            ),
+            expected
+        );
+        assert_eq!(
+            merge(
+                "[vars.site]\nnav = { json = \"nav.json\" }",
+                &["cryogen", "single", "--yaml", "site.nav:nav.yml"],
+            ),
+            vec!["cryogen", "single", "--yaml", "site.nav:nav.yml"]
+        );
+    }
+
+    #[test]
+    fn test_merge_skips_global_option_values() {
+        let config = "[options]\nmarkdown-tables = true";
+
+        assert_eq!(
+            merge(config, &["cryogen", "--config", "single", "single"]),
+            vec![
+                "cryogen",
+                "--config",
+                "single",
+                "single",
+                "--markdown-tables"
+            ]
+        );
+        assert_eq!(
+            merge(config, &["cryogen", "-c", "single", "single"]),
+            vec!["cryogen", "-c", "single", "single", "--markdown-tables"]
+        );
+        assert_eq!(
+            merge(config, &["cryogen", "--color", "never", "single"]),
+            vec!["cryogen", "--color", "never", "single", "--markdown-tables"]
+        );
+        assert_eq!(
+            merge(config, &["cryogen", "--color=never", "single"]),
+            vec!["cryogen", "--color=never", "single", "--markdown-tables"]
+        );
+    }
+
+    #[test]
+    fn test_missing_subcommand() {
+        let argv: Vec<OsString> = vec!["cryogen".into(), "-c".into(), "single".into()];
+
+        assert_eq!(subcommand_index(&argv, "single"), None);
+        assert_eq!(subcommand_index(&argv[..1], "single"), None);
+    }
+}
@@ -0,0 +1,260 @@
+use ansi_term::{Colour, Style};
+use app::STDIN;
+use atty::{self, Stream};
+use clap::{Arg, ArgMatches};
+use cryogen_prelude::Error;
+use std::{env, fs};
+
+/// The name of the argument that chooses when to color diagnostics.
+///
+pub const COLOR: &str = "color";
+
+/// The argument that chooses when to color diagnostics.
+///
+pub fn color_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name(COLOR)
+        .long(COLOR)
+        .help("When to color error messages")
+        .takes_value(true)
+        .possible_values(&["auto", "always", "never"])
+        .default_value("auto")
+        .global(true)
+}
+
+/// Returns whether diagnostics should be colored. By default, they are only
+/// colored if stderr is a terminal, and `NO_COLOR` isn't set.
+///
+pub fn use_color<'a>(args: &ArgMatches<'a>) -> bool {
+    match args.value_of(COLOR) {
+        Some("always") => true,
+        Some("never") => false,
+        _ => atty::is(Stream::Stderr) && env::var_os("NO_COLOR").is_none(),
+    }
+}
+
+/// Paints text with a style, if color is enabled.
+///
+fn paint(style: Style, text: &str, color: bool) -> String {
+    if color {
+        style.paint(text).to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+/// Reads a line (starting at 1) from a file. Files read from stdin can't be
+/// read again, so their lines are never returned.
+///
+fn source_line(path: &str, line: usize) -> Option<String> {
+    if path == STDIN || line == 0 {
+        return None;
+    }
+
+    fs::read_to_string(path)
+        .ok()?
+        .lines()
+        .nth(line - 1)
+        .map(str::to_string)
+}
+
+/// Formats the location of a parse error, followed by the offending line of
+/// the file and a caret under the column.
+///
+fn snippet(path: &str, line: Option<usize>, column: Option<usize>, color: bool, out: &mut String) {
+    let blue = Colour::Blue.bold();
+    let location = match (line, column) {
+        (Some(line), Some(column)) => format!("{}:{}:{}", path, line, column),
+        (Some(line), None) => format!("{}:{}", path, line),
+        _ => path.to_string(),
+    };
+    let number = line.map(|line| line.to_string()).unwrap_or_default();
+    let gutter = " ".repeat(number.len().max(1));
+
+    out.push_str(&format!(
+        "{}{} {}\n",
+        gutter,
+        paint(blue, "-->", color),
+        location
+    ));
+
+    let text = match line.and_then(|line| source_line(path, line)) {
+        Some(text) => text,
+        None => return,
+    };
+
+    out.push_str(&format!("{} {}\n", gutter, paint(blue, "|", color)));
+    out.push_str(&format!(
+        "{} {} {}\n",
+        paint(blue, &number, color),
+        paint(blue, "|", color),
+        text
+    ));
+
+    if let Some(column) = column {
+        // Tabs are kept, so the caret lines up with the text above it.
+        //
+        let padding: String = text
+            .chars()
+            .take(column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        out.push_str(&format!(
+            "{} {} {}{}\n",
+            gutter,
+            paint(blue, "|", color),
+            padding,
+            paint(Colour::Red.bold(), "^", color)
+        ));
+    }
+}
+
+/// Formats an error for the terminal. Parse errors show the line of the file
+/// they occurred on, render errors show every cause reported by Tera, and
+/// schema errors show every violation.
+///
+pub fn format(e: &Error, color: bool) -> String {
+    let mut out = paint(Colour::Red.bold(), "error", color);
+
+    match *e {
+        Error::Parse {
+            plugin,
+            ref path,
+            line,
+            column,
+            ref message,
+        } => {
+            let header = format!("failed to parse with {} plugin: {}", plugin, message);
+
+            out.push_str(&format!(
+                ": {}\n",
+                paint(Style::new().bold(), &header, color)
+            ));
+
+            if let Some(ref path) = *path {
+                snippet(path, line, column, color, &mut out);
+            }
+        }
+        Error::Render {
+            ref template,
+            ref message,
+            ref causes,
+        } => {
+            let header = format!("failed to render template ({}): {}", template, message);
+
+            out.push_str(&format!(
+                ": {}\n",
+                paint(Style::new().bold(), &header, color)
+            ));
+
+            for cause in causes {
+                let mut lines = cause.lines();
+
+                if let Some(first) = lines.next() {
+                    out.push_str(&format!(
+                        "  {} {}\n",
+                        paint(Colour::Blue.bold(), "=", color),
+                        first
+                    ));
+                }
+
+                for line in lines {
+                    out.push_str(&format!("    {}\n", line));
+                }
+            }
+        }
+        Error::Schema {
+            ref schemas,
+            ref violations,
+        } => {
+            let header = format!("data doesn't match schema ({})", schemas.join(", "));
+
+            out.push_str(&format!(
+                ": {}\n",
+                paint(Style::new().bold(), &header, color)
+            ));
+
+            for violation in violations {
+                out.push_str(&format!(
+                    "  {} {}\n",
+                    paint(Colour::Blue.bold(), "=", color),
+                    violation
+                ));
+            }
+        }
+        ref e => {
+            let header = e.to_string();
+
+            out.push_str(&format!(
+                ": {}\n",
+                paint(Style::new().bold(), &header, color)
+            ));
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format;
+    use cryogen_prelude::Error;
+    use std::{env, fs, process};
+
+    fn parse_error(path: &str, line: usize, column: usize) -> Error {
+        Error::parse("json", "expected value")
+            .at(line, column)
+            .with_path(path)
+    }
+
+    #[test]
+    fn test_caret_under_column() {
+        let path = env::temp_dir().join(format!("cryogen-diagnostic-{}.json", process::id()));
+        let path = path.to_string_lossy().into_owned();
+
+        fs::write(&path, "{\n\t\"a\": ,\n}\n").unwrap();
+
+        let formatted = format(&parse_error(&path, 2, 8), false);
+
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            formatted,
+            format!(
+                "error: failed to parse with json plugin: expected value\n \
+                 --> {}:2:8\n  \
+                 |\n\
+                 2 | \t\"a\": ,\n  \
+                 | \t      ^\n",
+                path
+            )
+        );
+    }
+
+    #[test]
+    fn test_stdin_has_no_snippet() {
+        assert_eq!(
+            format(&parse_error("-", 2, 8), false),
+            "error: failed to parse with json plugin: expected value\n --> -:2:8\n"
+        );
+    }
+
+    #[test]
+    fn test_other_errors() {
+        assert_eq!(
+            format(&Error::Usage("no template specified".into()), false),
+            "error: no template specified\n"
+        );
+        assert_eq!(
+            format(
+                &Error::Schema {
+                    schemas: vec!["a.json".into(), "b.json".into()],
+                    violations: vec!["x.yml at the root: bad".into()],
+                },
+                false
+            ),
+            "error: data doesn't match schema (a.json, b.json)\n  = x.yml at the root: bad\n"
+        );
+        assert!(format(&Error::Usage("x".into()), true).contains("\u{1b}["));
+    }
+}
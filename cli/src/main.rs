@@ -1,5 +1,15 @@
+extern crate ansi_term;
+extern crate atty;
 #[macro_use]
 extern crate clap;
+#[cfg(feature = "csv")]
+extern crate cryogen_plugin_csv;
+#[cfg(feature = "dir")]
+extern crate cryogen_plugin_dir;
+#[cfg(feature = "env")]
+extern crate cryogen_plugin_env;
+#[cfg(feature = "front-matter")]
+extern crate cryogen_plugin_front_matter;
 #[cfg(feature = "github-markdown")]
 extern crate cryogen_plugin_github_markdown;
 #[cfg(feature = "json")]
@@ -7,25 +17,95 @@ extern crate cryogen_plugin_json;
 #[cfg(feature = "markdown")]
 extern crate cryogen_plugin_markdown;
 extern crate cryogen_plugin_primitives;
+#[cfg(feature = "toml")]
+extern crate cryogen_plugin_toml;
 #[cfg(feature = "yaml")]
 extern crate cryogen_plugin_yaml;
 extern crate cryogen_prelude;
+extern crate glob;
+extern crate notify;
 extern crate tera;
+extern crate tiny_http;
+extern crate toml;
 
 #[macro_use]
 mod app;
+#[cfg(any(feature = "markdown", feature = "github-markdown"))]
+mod build;
+mod config;
+mod diagnostic;
+mod multi;
+mod serve;
 mod single;
+mod templates;
+mod watch;
 
-fn main() -> Result<(), String> {
+use clap::ArgMatches;
+use config::Config;
+use cryogen_prelude::Error;
+use std::{env, ffi::OsString, process};
+
+fn app<'a, 'b>() -> clap::App<'a, 'b> {
     let app = clap::App::new("Cryogen")
         .version(crate_version!())
         .author("Ferris T. <ferristseng@fastmail.fm>")
         .about("Render a tera template with file data")
+        .arg(
+            clap::Arg::with_name(config::CONFIG)
+                .long(config::CONFIG)
+                .short("c")
+                .help("The project config file to read arguments from (defaults to cryogen.toml)")
+                .takes_value(true),
+        )
+        .arg(diagnostic::color_arg())
         .subcommand(single::Command::app())
-        .get_matches();
+        .subcommand(multi::Command::app())
+        .subcommand(serve::Command::app());
+
+    #[cfg(any(feature = "markdown", feature = "github-markdown"))]
+    let app = app.subcommand(build::Command::app());
+
+    app
+}
+
+/// Returns the exit code for an error, so scripts can tell the kinds of
+/// failure apart.
+///
+fn exit_code(e: &Error) -> i32 {
+    match *e {
+        Error::Usage(_) => 2,
+        Error::Mapping(_) => 3,
+        Error::Io { .. } => 4,
+        Error::Parse { .. } => 5,
+        Error::Render { .. } => 6,
+        Error::Schema { .. } => 7,
+    }
+}
 
-    match app.subcommand() {
+fn run<'a>(args: ArgMatches<'a>, argv: Vec<OsString>) -> Result<(), Error> {
+    let args = match Config::load(&args)? {
+        Some(config) => app().get_matches_from(config.merge(&args, argv)?),
+        None => args,
+    };
+
+    match args.subcommand() {
         ("single", Some(args)) => single::Command::exec(args),
-        (cmd, _) => Err(format!("unexpected subcommand ({})", cmd)),
+        ("multi", Some(args)) => multi::Command::exec(args),
+        ("serve", Some(args)) => serve::Command::exec(args),
+        #[cfg(any(feature = "markdown", feature = "github-markdown"))]
+        ("build", Some(args)) => build::Command::exec(args),
+        (cmd, _) => Err(Error::Usage(format!("unexpected subcommand ({})", cmd))),
+    }
+}
+
+fn main() {
+    let argv: Vec<OsString> = env::args_os().collect();
+    let args = app().get_matches_from(argv.clone());
+    let color = diagnostic::use_color(&args);
+
+    if let Err(e) = run(args, argv) {
+        eprint!("{}", diagnostic::format(&e, color));
+
+        process::exit(exit_code(&e));
     }
 }
@@ -0,0 +1,167 @@
+use app::{exec_plugins, output_path, plugin_args, print_report, read_template, write_output};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use cryogen_prelude::Error;
+use glob::glob;
+use std::path::{Path, PathBuf};
+use templates::{templates_args, Templates};
+use tera::Context;
+
+const OUTPUT_DIR: &str = "output-dir";
+const TEMPLATE_ROOT: &str = "template-root";
+
+/// Expands a list of template paths or glob patterns into the paths of every
+/// matched template.
+///
+fn expand_templates<'a, I>(patterns: I) -> Result<Vec<PathBuf>, Error>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let mut templates = Vec::new();
+
+    for pattern in patterns {
+        let paths = glob(pattern)
+            .map_err(|e| Error::Usage(format!("invalid template pattern ({}): {}", pattern, e)))?;
+        let len = templates.len();
+
+        for path in paths {
+            let path = path?;
+
+            if path.is_file() {
+                templates.push(path);
+            }
+        }
+
+        if templates.len() == len {
+            return Err(Error::Usage(format!("no templates matched ({})", pattern)));
+        }
+    }
+
+    Ok(templates)
+}
+
+/// Command to render many tera templates into an output directory.
+///
+pub struct Command;
+
+impl Command {
+    pub const COMMAND_NAME: &'static str = "multi";
+
+    pub const POSITIONAL: &'static str = "TEMPLATES";
+
+    pub fn app<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name(Self::COMMAND_NAME)
+            .about("Renders many templates into an output directory")
+            .arg(
+                Arg::with_name(Self::POSITIONAL)
+                    .help("The tera templates (or glob patterns) to render")
+                    .multiple(true)
+                    .index(1),
+            )
+            .arg(
+                Arg::with_name(OUTPUT_DIR)
+                    .long(OUTPUT_DIR)
+                    .short("o")
+                    .help("The directory to write rendered templates to")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name(TEMPLATE_ROOT)
+                    .long(TEMPLATE_ROOT)
+                    .help("Directory stripped from template paths before mirroring them in the output directory")
+                    .takes_value(true),
+            )
+            .args(&templates_args())
+            .args(&plugin_args())
+    }
+
+    pub fn exec<'a>(args: &'a ArgMatches<'a>) -> Result<(), Error> {
+        let output_dir = args.value_of(OUTPUT_DIR).map(Path::new).ok_or_else(|| {
+            Error::Usage(
+                "no output directory specified on the command line or in the config".into(),
+            )
+        })?;
+        let template_root = args.value_of(TEMPLATE_ROOT).map(Path::new);
+        let templates = args.values_of(Self::POSITIONAL).ok_or_else(|| {
+            Error::Usage("no templates specified on the command line or in the config".into())
+        })?;
+        let templates = expand_templates(templates)?;
+        let mut tera = Templates::from_args(args)?;
+        let mut template_vars = Context::new();
+
+        print_report(exec_plugins(args, &mut template_vars)?, &mut None);
+
+        for template in templates {
+            let template_path = template.to_string_lossy();
+            let template_contents = read_template(&template_path)?;
+            let rendered = tera.render(&template_path, &template_contents, &template_vars)?;
+            let out = output_path(output_dir, template_root, &template)?;
+
+            write_output(&out, rendered.as_ref())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand_templates;
+    use app::output_path;
+    use std::{env, fs, path::Path, process};
+
+    #[test]
+    fn test_expand_templates() {
+        let root = env::temp_dir().join(format!("cryogen-multi-{}", process::id()));
+
+        for file in &["index.html", "posts/a.html", "posts/b.txt"] {
+            let path = root.join(file);
+
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "").unwrap();
+        }
+
+        let all = root
+            .join("**")
+            .join("*.html")
+            .to_string_lossy()
+            .into_owned();
+        let index = root.join("index.html").to_string_lossy().into_owned();
+        let missing = root.join("*.md").to_string_lossy().into_owned();
+        let templates = expand_templates(vec![&all[..], &index[..]].into_iter());
+        let unmatched = expand_templates(vec![&missing[..]].into_iter());
+        let invalid = expand_templates(vec!["[a"].into_iter());
+
+        fs::remove_dir_all(&root).unwrap();
+
+        let templates = templates.unwrap();
+        let out = Path::new("out");
+        let outputs: Vec<_> = templates
+            .iter()
+            .map(|template| output_path(out, Some(&root), template).unwrap())
+            .collect();
+
+        assert_eq!(
+            templates,
+            vec![
+                root.join("index.html"),
+                root.join("posts/a.html"),
+                root.join("index.html"),
+            ]
+        );
+        assert_eq!(
+            outputs,
+            vec![
+                out.join("index.html"),
+                out.join("posts/a.html"),
+                out.join("index.html"),
+            ]
+        );
+        assert!(unmatched.is_err());
+        assert!(invalid.is_err());
+        assert!(output_path(out, Some(Path::new("other")), &templates[0]).is_err());
+        assert_eq!(
+            output_path(out, None, Path::new("../site/./a.html")).unwrap(),
+            out.join("site/a.html")
+        );
+    }
+}
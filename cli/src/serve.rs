@@ -0,0 +1,345 @@
+use app::{plugin_args, plugin_paths, uses_stdin};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use cryogen_prelude::Error;
+use diagnostic::{self, use_color};
+use single;
+use std::{
+    fs,
+    io::{self, Cursor},
+    path::{Component, Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+use templates::{template_paths, templates_args};
+use tiny_http::{Header, Response, Server};
+use watch::watch;
+
+const HOST: &str = "host";
+const PORT: &str = "port";
+
+/// Path polled by the live reload script.
+///
+const VERSION_PATH: &str = "/__cryogen/version";
+
+/// Script injected into rendered pages, that reloads the page whenever the
+/// version reported by the server changes.
+///
+const RELOAD_SCRIPT: &str = r#"<script>
+(function () {
+  var version = null;
+  setInterval(function () {
+    fetch("/__cryogen/version")
+      .then(function (res) { return res.text(); })
+      .then(function (next) {
+        if (version !== null && next !== version) {
+          location.reload();
+        }
+        version = next;
+      })
+      .catch(function () {});
+  }, 1000);
+})();
+</script>
+"#;
+
+/// Escapes text to be embedded in HTML.
+///
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Adds the live reload script to the end of the page body.
+///
+fn inject_reload(mut html: String) -> String {
+    match html.rfind("</body>") {
+        Some(index) => html.insert_str(index, RELOAD_SCRIPT),
+        None => html.push_str(RELOAD_SCRIPT),
+    }
+
+    html
+}
+
+/// Builds a page describing a failed render.
+///
+fn error_page(template_path: &str, error: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head><title>Render error</title></head>\n<body>\n\
+         <h1>Failed to render {}</h1>\n<pre>{}</pre>\n</body>\n</html>\n",
+        escape_html(template_path),
+        escape_html(error)
+    )
+}
+
+/// Returns the content type of a static asset from its extension. Only
+/// stylesheets, scripts, images, and fonts are assets, so templates, data
+/// files, and the project config are never served.
+///
+fn content_type(path: &Path) -> Option<&'static str> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("css") => Some("text/css; charset=utf-8"),
+        Some("js") => Some("application/javascript; charset=utf-8"),
+        Some("svg") => Some("image/svg+xml"),
+        Some("png") => Some("image/png"),
+        Some("jpg") | Some("jpeg") => Some("image/jpeg"),
+        Some("gif") => Some("image/gif"),
+        Some("webp") => Some("image/webp"),
+        Some("ico") => Some("image/x-icon"),
+        Some("woff") => Some("font/woff"),
+        Some("woff2") => Some("font/woff2"),
+        _ => None,
+    }
+}
+
+/// Resolves a request path to a static asset under the root directory.
+/// Paths that would escape the root directory, dotfiles, files that aren't
+/// assets, and files read to render the page aren't resolved.
+///
+fn static_path(root: &Path, url_path: &str, hidden: &[PathBuf]) -> Option<PathBuf> {
+    let relative = Path::new(url_path.trim_start_matches('/'));
+
+    if !relative.components().all(|c| match c {
+        Component::Normal(name) => !name.to_string_lossy().starts_with('.'),
+        _ => false,
+    }) {
+        return None;
+    }
+
+    let path = root.join(relative);
+
+    content_type(&path)?;
+
+    match fs::canonicalize(&path) {
+        Ok(ref canonical) if !hidden.contains(canonical) => Some(path),
+        _ => None,
+    }
+}
+
+/// Builds a response with a body and content type.
+///
+fn respond(status: u16, content_type: &str, body: Vec<u8>) -> Response<Cursor<Vec<u8>>> {
+    let header = Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes())
+        .expect("content types are static ASCII strings, which are valid header values");
+
+    Response::from_data(body)
+        .with_status_code(status)
+        .with_header(header)
+}
+
+/// Command to preview a rendered template over HTTP, reloading the page
+/// whenever the template or a data file changes.
+///
+pub struct Command;
+
+impl Command {
+    pub const COMMAND_NAME: &'static str = "serve";
+
+    pub const POSITIONAL: &'static str = single::Command::POSITIONAL;
+
+    pub fn app<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name(Self::COMMAND_NAME)
+            .about(
+                "Serves a rendered template, reloading it when files change. Stylesheets, \
+                 scripts, images, and fonts next to the template are served too",
+            )
+            .arg(single::Command::template_arg())
+            .args(&templates_args())
+            .arg(
+                Arg::with_name(HOST)
+                    .long(HOST)
+                    .help("The address to listen on")
+                    .takes_value(true)
+                    .default_value("127.0.0.1"),
+            )
+            .arg(
+                Arg::with_name(PORT)
+                    .long(PORT)
+                    .short("p")
+                    .help("The port to listen on")
+                    .takes_value(true)
+                    .default_value("8000"),
+            )
+            .args(&plugin_args())
+    }
+
+    pub fn exec<'a>(args: &'a ArgMatches<'a>) -> Result<(), Error> {
+        let template_path = args.value_of(Self::POSITIONAL).ok_or_else(|| {
+            Error::Usage("no template specified on the command line or in the config".into())
+        })?;
+
+        if uses_stdin(args)? {
+            return Err(Error::Usage(
+                "stdin can only be read once, so it can't be served".into(),
+            ));
+        }
+
+        let root = Path::new(template_path)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let host = args.value_of(HOST).unwrap();
+        let port = args
+            .value_of(PORT)
+            .unwrap()
+            .parse::<u16>()
+            .map_err(|e| Error::Usage(format!("invalid port: {}", e)))?;
+        let server = Server::http((host, port)).map_err(|e| {
+            Error::from(io::Error::other(e.to_string())).with_path(format!("{}:{}", host, port))
+        })?;
+        let version = Arc::new(AtomicUsize::new(0));
+        let mut files = plugin_paths(args)?;
+
+        files.extend(template_paths(args)?);
+
+        files.push(PathBuf::from(template_path));
+
+        let hidden: Vec<PathBuf> = files
+            .iter()
+            .filter_map(|file| fs::canonicalize(file).ok())
+            .collect();
+        let color = use_color(args);
+
+        {
+            let version = version.clone();
+
+            thread::spawn(move || {
+                let watched = watch(&files, color, || {
+                    version.fetch_add(1, Ordering::SeqCst);
+
+                    Ok(())
+                });
+
+                if let Err(e) = watched {
+                    eprint!("{}", diagnostic::format(&e, color));
+                    eprintln!("Live reload stopped, restart serve to re-enable it");
+                }
+            });
+        }
+
+        eprintln!("Serving {} on http://{}:{}/", template_path, host, port);
+
+        let mut report = None;
+
+        for request in server.incoming_requests() {
+            let url_path = request.url().split('?').next().unwrap_or("/").to_string();
+            let response = match &url_path[..] {
+                "/" => match single::Command::render_to_string(args, &mut report) {
+                    Ok(html) => {
+                        respond(200, "text/html; charset=utf-8", inject_reload(html).into())
+                    }
+                    Err(e) => respond(
+                        500,
+                        "text/html; charset=utf-8",
+                        inject_reload(error_page(template_path, &diagnostic::format(&e, false)))
+                            .into(),
+                    ),
+                },
+                VERSION_PATH => respond(
+                    200,
+                    "text/plain; charset=utf-8",
+                    version.load(Ordering::SeqCst).to_string().into(),
+                ),
+                path => match static_path(&root, path, &hidden).and_then(|file| {
+                    let content_type = content_type(&file)?;
+
+                    fs::read(&file).ok().map(|body| (content_type, body))
+                }) {
+                    Some((content_type, body)) => respond(200, content_type, body),
+                    None => respond(404, "text/plain; charset=utf-8", b"Not Found".to_vec()),
+                },
+            };
+
+            if let Err(e) = request.respond(response) {
+                eprintln!("Error: failed to respond to request ({}): {}", url_path, e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{content_type, static_path};
+    use std::{env, fs, path::Path};
+
+    #[test]
+    fn test_content_type() {
+        assert_eq!(
+            content_type(Path::new("a/style.css")),
+            Some("text/css; charset=utf-8")
+        );
+        assert_eq!(content_type(Path::new("index.html")), None);
+        assert_eq!(content_type(Path::new("data.json")), None);
+        assert_eq!(content_type(Path::new("cryogen.toml")), None);
+        assert_eq!(content_type(Path::new("Makefile")), None);
+    }
+
+    #[test]
+    fn test_static_path() {
+        let root = env::temp_dir().join(format!("cryogen-serve-{}", ::std::process::id()));
+        let files = [
+            "style.css",
+            "js/app.js",
+            ".hidden.css",
+            ".git/logo.png",
+            "theme.css",
+            "data.json",
+            "index.html",
+        ];
+
+        for file in &files {
+            let path = root.join(file);
+
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "").unwrap();
+        }
+
+        let hidden = vec![fs::canonicalize(root.join("theme.css")).unwrap()];
+        let resolve = |url: &str| static_path(&root, url, &hidden);
+        let served = (
+            resolve("/style.css"),
+            resolve("/js/app.js"),
+            resolve("//style.css"),
+        );
+        let rejected = [
+            resolve("/../style.css"),
+            resolve("/js/../style.css"),
+            resolve("/./style.css"),
+            resolve("/%2e%2e/style.css"),
+            resolve("/js%2f..%2fstyle.css"),
+            resolve("/.hidden.css"),
+            resolve("/.git/logo.png"),
+            resolve("/theme.css"),
+            resolve("/data.json"),
+            resolve("/index.html"),
+            resolve("/missing.css"),
+            resolve("/"),
+        ];
+
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(served.0, Some(root.join("style.css")));
+        assert_eq!(served.1, Some(root.join("js/app.js")));
+        assert_eq!(served.2, Some(root.join("style.css")));
+
+        for (i, path) in rejected.iter().enumerate() {
+            assert_eq!(*path, None, "rejected path {} was resolved", i);
+        }
+    }
+}
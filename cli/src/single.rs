@@ -1,109 +1,108 @@
-use app::open_template;
+use app::{
+    exec_plugins, open_template, plugin_args, plugin_paths, print_report, uses_stdin, write_output,
+};
 use clap::{App, Arg, ArgMatches, SubCommand};
-use cryogen_prelude::{CompileVariablePlugin, Interpretation, Source, VarMapping};
-use std::{fs::File, io::{stdout, Write}};
-use tera::{Context, Tera};
+use cryogen_prelude::Error;
+use diagnostic;
+use std::{
+    io::{stdout, Write},
+    path::{Path, PathBuf},
+};
+use templates::{template_paths, templates_args, Templates};
+use tera::Context;
+use watch::watch;
 
-/// Executes a plugin.
-///
-fn exec_plugin<'a, T>(args: &ArgMatches<'a>, template_vars: &mut Context) -> Result<(), String>
-where
-    T: CompileVariablePlugin,
-{
-    let plugin = T::from_args(args);
-
-    let mappings = args.values_of(T::ARG_NAME).into_iter().flat_map(|a| a);
-    for mapping in mappings.map(VarMapping::from_str) {
-        let mapping = mapping?;
-        let src = match T::ARG_INTERPRETATION {
-            Interpretation::Raw => Source::Raw(mapping.arg_value(), 0),
-            Interpretation::Path => {
-                let file = File::open(mapping.arg_value()).map_err(|e| e.to_string())?;
-
-                Source::File(file)
-            }
-        };
-
-        template_vars.add(mapping.var_name(), &plugin.read(src)?);
-    }
-
-    Ok(())
-}
+const OUTPUT: &str = "output";
+const WATCH: &str = "watch";
 
 /// Command to render a single output file from a tera template.
 ///
 pub struct Command;
 
 impl Command {
-    const COMMAND_NAME: &'static str = "single";
+    pub const COMMAND_NAME: &'static str = "single";
 
-    pub fn app<'a, 'b>() -> App<'a, 'b> {
-        let plugins = plugins! {
-            ::cryogen_plugin_primitives::StringPlugin;
-            ::cryogen_plugin_primitives::FloatPlugin;
-            ::cryogen_plugin_primitives::IntPlugin;
-            ::cryogen_plugin_primitives::BooleanPlugin;
-            #[cfg(feature = "github-markdown")]
-            ::cryogen_plugin_github_markdown::GithubMarkdownPlugin;
-            #[cfg(feature = "json")]
-            ::cryogen_plugin_json::JsonPlugin;
-            #[cfg(feature = "markdown")]
-            ::cryogen_plugin_markdown::MarkdownPlugin;
-            #[cfg(feature = "yaml")]
-            ::cryogen_plugin_yaml::YamlPlugin;
-        };
+    pub const POSITIONAL: &'static str = "TEMPLATE";
 
+    /// The template argument, shared with other subcommands that render a
+    /// single template.
+    ///
+    pub fn template_arg<'a, 'b>() -> Arg<'a, 'b> {
+        Arg::with_name(Self::POSITIONAL)
+            .help("The tera template to render")
+            .index(1)
+    }
+
+    pub fn app<'a, 'b>() -> App<'a, 'b> {
         SubCommand::with_name(Self::COMMAND_NAME)
             .about("Renders a single output file")
+            .arg(Self::template_arg())
+            .args(&templates_args())
+            .arg(
+                Arg::with_name(OUTPUT)
+                    .long(OUTPUT)
+                    .short("o")
+                    .help("The file to write the output to, instead of stdout")
+                    .takes_value(true),
+            )
             .arg(
-                Arg::with_name("TEMPLATE")
-                    .help("The tera template to render")
-                    .required(true)
-                    .index(1),
+                Arg::with_name(WATCH)
+                    .long(WATCH)
+                    .short("w")
+                    .help("Render again whenever the template or a data file changes"),
             )
-            .args(&plugins)
+            .args(&plugin_args())
     }
 
-    pub fn exec<'a>(args: &'a ArgMatches<'a>) -> Result<(), String> {
-        let (template_path, template_contents) = open_template(&args);
-        let mut template_vars = Context::new();
+    pub fn exec<'a>(args: &'a ArgMatches<'a>) -> Result<(), Error> {
+        if args.is_present(WATCH) {
+            if uses_stdin(args)? {
+                return Err(Error::Usage(
+                    "stdin can only be read once, so it can't be watched".into(),
+                ));
+            }
+
+            let mut files = plugin_paths(args)?;
 
-        macro_rules! exec {
-            ( $( $(#[$feature:meta])* $plug:ty );*; ) => {
-                $(
-                    $(#[$feature])*
-                    exec_plugin::<$plug>(&args, &mut template_vars)?;
-                )*
+            files.extend(template_paths(args)?);
+
+            if let Some(template_path) = args.value_of(Self::POSITIONAL) {
+                files.push(PathBuf::from(template_path));
             }
-        }
 
-        exec! {
-            ::cryogen_plugin_primitives::StringPlugin;
-            ::cryogen_plugin_primitives::FloatPlugin;
-            ::cryogen_plugin_primitives::IntPlugin;
-            ::cryogen_plugin_primitives::BooleanPlugin;
-            #[cfg(feature = "github-markdown")]
-            ::cryogen_plugin_github_markdown::GithubMarkdownPlugin;
-            #[cfg(feature = "json")]
-            ::cryogen_plugin_json::JsonPlugin;
-            #[cfg(feature = "markdown")]
-            ::cryogen_plugin_markdown::MarkdownPlugin;
-            #[cfg(feature = "yaml")]
-            ::cryogen_plugin_yaml::YamlPlugin;
-        }
+            let mut report = None;
 
-        Tera::one_off(&template_contents, &template_vars, false)
-            .map_err(|e| {
-                format!(
-                    "failed one time render for template ({}): {}",
-                    template_path,
-                    e.description()
-                )
-            })
-            .and_then(|rendered| {
-                stdout()
-                    .write_all(rendered.as_ref())
-                    .map_err(|e| e.to_string())
+            watch(&files, diagnostic::use_color(args), || {
+                Self::render(args, &mut report)
             })
+        } else {
+            Self::render(args, &mut None)
+        }
+    }
+
+    /// Renders the template with the variables from every plugin. The merge
+    /// report is printed if it differs from the last one printed.
+    ///
+    pub fn render_to_string<'a>(
+        args: &'a ArgMatches<'a>,
+        report: &mut Option<String>,
+    ) -> Result<String, Error> {
+        let mut tera = Templates::from_args(args)?;
+        let mut template_vars = Context::new();
+
+        print_report(exec_plugins(args, &mut template_vars)?, report);
+
+        let (template_path, template_contents) = open_template(args)?;
+
+        tera.render(template_path, &template_contents, &template_vars)
+    }
+
+    fn render<'a>(args: &'a ArgMatches<'a>, report: &mut Option<String>) -> Result<(), Error> {
+        let rendered = Self::render_to_string(args, report)?;
+
+        match args.value_of(OUTPUT) {
+            Some(path) => write_output(Path::new(path), rendered.as_ref()),
+            None => stdout().write_all(rendered.as_ref()).map_err(Error::from),
+        }
     }
 }
@@ -0,0 +1,164 @@
+use app::extend_tera;
+use clap::{Arg, ArgMatches};
+use cryogen_prelude::Error;
+use glob::glob;
+use std::path::PathBuf;
+use tera::{self, Context, Tera};
+
+/// The name of the argument to supply a glob pattern of templates.
+///
+pub const TEMPLATES: &str = "templates";
+
+/// The name of the argument that chooses when to escape HTML in rendered
+/// values.
+///
+const AUTOESCAPE: &str = "autoescape";
+
+/// Templates with these extensions are escaped unless autoescaping is
+/// turned off.
+///
+const AUTOESCAPE_SUFFIXES: &[&str] = &[".html", ".htm", ".xml"];
+
+/// The arguments that control how templates are loaded and rendered.
+///
+pub fn templates_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name(TEMPLATES)
+            .long(TEMPLATES)
+            .help(
+                "Glob pattern of templates that can be extended, included, or imported from \
+                 (e.g. 'layouts/**/*.html')",
+            )
+            .takes_value(true),
+        Arg::with_name(AUTOESCAPE)
+            .long(AUTOESCAPE)
+            .help(
+                "When to escape HTML in rendered values (auto escapes .html, .htm, and .xml \
+                 templates). Use the `safe` filter to render a value as is",
+            )
+            .takes_value(true)
+            .possible_values(&["auto", "always", "never"])
+            .default_value("auto"),
+    ]
+}
+
+/// Returns the paths of every template matched by the templates argument.
+///
+pub fn template_paths<'a>(args: &'a ArgMatches<'a>) -> Result<Vec<PathBuf>, Error> {
+    let pattern = match args.value_of(TEMPLATES) {
+        Some(pattern) => pattern,
+        None => return Ok(Vec::new()),
+    };
+    let paths = glob(pattern)
+        .map_err(|e| Error::Usage(format!("invalid templates pattern ({}): {}", pattern, e)))?;
+
+    Ok(paths
+        .filter_map(Result::ok)
+        .filter(|path| path.is_file())
+        .collect())
+}
+
+/// Returns whether a Tera error was caused by a template that isn't loaded.
+///
+fn is_missing_template(message: &str) -> bool {
+    (message.starts_with("Template '") && message.ends_with("' not found"))
+        || message.ends_with("which doesn't exist or isn't loaded.")
+        || message.ends_with("which isn't present in Tera")
+}
+
+/// Renders templates, which can extend, include, or import macros from the
+/// templates matched by the templates argument.
+///
+pub struct Templates {
+    tera: Tera,
+    pattern: Option<String>,
+    loaded: Vec<String>,
+}
+
+impl Templates {
+    /// Loads the templates matched by the templates argument, if it is
+    /// supplied, and registers the filters and functions of every enabled
+    /// plugin.
+    ///
+    pub fn from_args<'a>(args: &'a ArgMatches<'a>) -> Result<Templates, Error> {
+        let pattern = args.value_of(TEMPLATES).map(str::to_string);
+        let mut tera = match pattern {
+            Some(ref pattern) => {
+                let tera = Tera::new(pattern).map_err(|e| Error::Render {
+                    template: pattern.clone(),
+                    message: e.to_string(),
+                    causes: e.iter().skip(1).map(|cause| cause.to_string()).collect(),
+                })?;
+
+                if tera.templates.is_empty() {
+                    return Err(Error::Usage(format!("no templates matched ({})", pattern)));
+                }
+
+                tera
+            }
+            None => Tera::default(),
+        };
+
+        let mut loaded: Vec<String> = tera.templates.keys().cloned().collect();
+
+        loaded.sort();
+        extend_tera(args, &mut tera);
+
+        // Every template name ends with an empty suffix.
+        //
+        tera.autoescape_on(match args.value_of(AUTOESCAPE) {
+            Some("always") => vec![""],
+            Some("never") => vec![],
+            _ => AUTOESCAPE_SUFFIXES.to_vec(),
+        });
+
+        Ok(Templates {
+            tera,
+            pattern,
+            loaded,
+        })
+    }
+
+    /// Renders the contents of a tera template with the supplied variables.
+    ///
+    pub fn render(
+        &mut self,
+        template_path: &str,
+        template_contents: &str,
+        template_vars: &Context,
+    ) -> Result<String, Error> {
+        let rendered = match self.tera.add_raw_template(template_path, template_contents) {
+            Ok(()) => self.tera.render(template_path, template_vars),
+            Err(e) => Err(e),
+        };
+
+        rendered.map_err(|e| self.render_error(template_path, &e))
+    }
+
+    /// Converts a Tera error, explaining which templates are available if a
+    /// template couldn't be found.
+    ///
+    fn render_error(&self, template_path: &str, e: &tera::Error) -> Error {
+        let mut causes: Vec<String> = e.iter().skip(1).map(|cause| cause.to_string()).collect();
+
+        if e.iter().any(|e| is_missing_template(&e.to_string())) {
+            causes.push(match self.pattern {
+                Some(ref pattern) => format!(
+                    "templates loaded from ({}): {}",
+                    pattern,
+                    self.loaded.join(", ")
+                ),
+                None => format!(
+                    "use --{} to load templates that can be extended, included, or imported from",
+                    TEMPLATES
+                ),
+            });
+        }
+
+        Error::Render {
+            template: template_path.to_string(),
+            message: e.to_string(),
+            causes,
+        }
+    }
+}
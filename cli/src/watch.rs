@@ -0,0 +1,129 @@
+use cryogen_prelude::Error;
+use diagnostic;
+use notify::{self, DebouncedEvent, PollWatcher, RecursiveMode, Watcher};
+use std::{
+    collections::HashSet,
+    io::{self, stderr, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::channel,
+    time::Duration,
+};
+
+/// How long to wait for events to settle before re-rendering.
+///
+const DEBOUNCE_MS: u64 = 100;
+
+/// How often to check files for changes when falling back to polling.
+///
+const POLL_MS: u64 = 500;
+
+/// Returns an absolute path for a file, even if it doesn't currently exist
+/// (for example, when an editor is replacing it).
+///
+fn absolute(path: &Path) -> Result<PathBuf, Error> {
+    let parent = match path.parent() {
+        Some(parent) if parent != Path::new("") => parent,
+        _ => Path::new("."),
+    };
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::Usage(format!("cannot watch ({})", path.display())))?;
+
+    parent
+        .canonicalize()
+        .map(|parent| parent.join(file_name))
+        .map_err(|e| Error::from(e).with_path(path.to_string_lossy()))
+}
+
+/// Watches the parent directories of a set of files. Directories are watched
+/// instead of the files themselves, so files that are replaced on save keep
+/// being tracked.
+///
+fn watch_dirs<W>(watcher: &mut W, files: &HashSet<PathBuf>) -> notify::Result<()>
+where
+    W: Watcher,
+{
+    let dirs: HashSet<&Path> = files.iter().filter_map(|file| file.parent()).collect();
+
+    for dir in dirs {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+
+    Ok(())
+}
+
+/// Converts an error from the watcher.
+///
+fn watch_error(e: notify::Error) -> Error {
+    match e {
+        notify::Error::Io(e) => Error::from(e),
+        e => Error::from(io::Error::other(e.to_string())),
+    }
+}
+
+/// Reports an error without exiting.
+///
+fn report(result: Result<(), Error>, color: bool) {
+    if let Err(e) = result {
+        let _ = write!(stderr(), "{}", diagnostic::format(&e, color));
+    }
+}
+
+/// Calls `render` once, and then again every time one of the files changes.
+/// Errors are reported (colored if `color` is set), but do not stop
+/// watching.
+///
+/// Uses the native notification mechanism for the platform (inotify on
+/// Linux), and falls back to polling if it is unavailable.
+///
+pub fn watch<F>(files: &[PathBuf], color: bool, mut render: F) -> Result<(), Error>
+where
+    F: FnMut() -> Result<(), Error>,
+{
+    let files = files
+        .iter()
+        .map(|file| absolute(file))
+        .collect::<Result<HashSet<_>, _>>()?;
+    let (tx, rx) = channel();
+    let native = notify::watcher(tx.clone(), Duration::from_millis(DEBOUNCE_MS))
+        .and_then(|mut watcher| watch_dirs(&mut watcher, &files).map(|_| watcher));
+
+    // The watchers have to be kept alive for events to be sent.
+    //
+    let _watchers = match native {
+        Ok(watcher) => (Some(watcher), None),
+        Err(_) => {
+            let mut watcher =
+                PollWatcher::new(tx, Duration::from_millis(POLL_MS)).map_err(watch_error)?;
+
+            watch_dirs(&mut watcher, &files).map_err(watch_error)?;
+
+            (None, Some(watcher))
+        }
+    };
+
+    report(render(), color);
+
+    for event in rx {
+        let changed = match event {
+            DebouncedEvent::Create(ref path)
+            | DebouncedEvent::Write(ref path)
+            | DebouncedEvent::Chmod(ref path)
+            | DebouncedEvent::Remove(ref path)
+            | DebouncedEvent::Rename(_, ref path) => files.contains(path),
+            DebouncedEvent::Rescan => true,
+            DebouncedEvent::Error(e, _) => {
+                report(Err(watch_error(e)), color);
+
+                false
+            }
+            DebouncedEvent::NoticeWrite(_) | DebouncedEvent::NoticeRemove(_) => false,
+        };
+
+        if changed {
+            report(render(), color);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,227 @@
+extern crate clap;
+#[macro_use]
+extern crate cryogen_prelude;
+extern crate csv;
+extern crate serde_json;
+
+use clap::{Arg, ArgMatches};
+use cryogen_prelude::{CompileVariablePlugin, Error, Interpretation, Source};
+use csv::{ErrorKind, ReaderBuilder, StringRecord};
+use serde_json::{Map, Number, Value};
+use std::{io::Read, str::FromStr};
+
+const CSV_NO_HEADER: &str = "csv-no-header";
+const CSV_INFER_TYPES: &str = "csv-infer-types";
+const CSV_DELIMITER: &str = "csv-delimiter";
+
+/// Converts a CSV error, keeping the line it occurred on.
+///
+fn csv_error(e: csv::Error) -> Error {
+    let line = e.position().map(|pos| pos.line() as usize);
+    let message = e.to_string();
+
+    match e.into_kind() {
+        ErrorKind::Io(e) => Error::from(e),
+        _ => Error::Parse {
+            plugin: CsvPlugin::PLUGIN_NAME,
+            path: None,
+            line,
+            column: None,
+            message,
+        },
+    }
+}
+
+pub struct CsvPlugin {
+    no_header: bool,
+    infer_types: bool,
+    delimiter: Option<String>,
+}
+
+impl CsvPlugin {
+    /// Returns the field delimiter. Tabs can be written as `\t` or `tab`.
+    ///
+    fn delimiter(&self) -> Result<u8, Error> {
+        match self.delimiter.as_ref().map(|d| &d[..]) {
+            None => Ok(b','),
+            Some("\\t") | Some("tab") => Ok(b'\t'),
+            Some(d) if d.len() == 1 => Ok(d.as_bytes()[0]),
+            Some(d) => Err(Error::Usage(format!(
+                "expected a single byte csv delimiter, got ({})",
+                d
+            ))),
+        }
+    }
+
+    /// Converts a field into a value, optionally inferring booleans and
+    /// numbers.
+    ///
+    fn field(&self, field: &str) -> Value {
+        if !self.infer_types {
+            return Value::String(field.to_string());
+        }
+
+        if let Ok(b) = bool::from_str(field) {
+            Value::Bool(b)
+        } else if let Ok(i) = i64::from_str(field) {
+            Value::Number(i.into())
+        } else if let Some(n) = f64::from_str(field).ok().and_then(Number::from_f64) {
+            Value::Number(n)
+        } else {
+            Value::String(field.to_string())
+        }
+    }
+
+    fn row(&self, record: &StringRecord) -> Value {
+        Value::Array(record.iter().map(|field| self.field(field)).collect())
+    }
+
+    fn keyed_row(&self, headers: &StringRecord, record: &StringRecord) -> Value {
+        let mut row = Map::new();
+
+        for (header, field) in headers.iter().zip(record.iter()) {
+            row.insert(header.to_string(), self.field(field));
+        }
+
+        Value::Object(row)
+    }
+}
+
+impl CompileVariablePlugin for CsvPlugin {
+    type RenderValue = Vec<Value>;
+
+    const PLUGIN_NAME: &'static str = "csv";
+
+    const ARG_NAME: &'static str = "csv";
+
+    const ARG_INTERPRETATION: Interpretation = Interpretation::Path;
+
+    const HELP: &'static str = "Assign variable to the rows of a CSV file";
+
+    #[inline]
+    fn additional_args() -> Vec<Arg<'static, 'static>> {
+        let mut args = args! {
+            CSV_NO_HEADER   ["Treat the first row as data, and render rows as lists"];
+            CSV_INFER_TYPES ["Convert numeric and boolean fields"];
+        };
+
+        args.push(
+            Arg::with_name(CSV_DELIMITER)
+                .long(CSV_DELIMITER)
+                .help("The field delimiter (use `tab` for TSV files)")
+                .takes_value(true),
+        );
+
+        args
+    }
+
+    #[inline]
+    fn from_args<'a>(args: &'a ArgMatches<'a>) -> CsvPlugin {
+        CsvPlugin {
+            no_header: args.is_present(CSV_NO_HEADER),
+            infer_types: args.is_present(CSV_INFER_TYPES),
+            delimiter: args.value_of(CSV_DELIMITER).map(str::to_string),
+        }
+    }
+
+    fn read<'a, R>(&self, src: Source<'a, R>) -> Result<Vec<Value>, Error>
+    where
+        R: Read,
+    {
+        let mut reader = ReaderBuilder::new()
+            .has_headers(!self.no_header)
+            .delimiter(self.delimiter()?)
+            .from_reader(src);
+        let headers = if self.no_header {
+            None
+        } else {
+            Some(reader.headers().map_err(csv_error)?.clone())
+        };
+        let mut rows = Vec::new();
+
+        for record in reader.records() {
+            let record = record.map_err(csv_error)?;
+
+            rows.push(match headers {
+                Some(ref headers) => self.keyed_row(headers, &record),
+                None => self.row(&record),
+            });
+        }
+
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CsvPlugin;
+    use clap::App;
+    use cryogen_prelude::{CompileVariablePlugin, Error, Source};
+    use serde_json::Value;
+    use std::io::Empty;
+
+    fn read(argv: &[&str], data: &str) -> Result<Value, Error> {
+        let args = App::new("test")
+            .args(&CsvPlugin::additional_args())
+            .get_matches_from(argv);
+        let src: Source<Empty> = Source::Raw(data, 0);
+
+        CsvPlugin::from_args(&args).read(src).map(Value::Array)
+    }
+
+    fn json(s: &str) -> Value {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_header() {
+        assert_eq!(
+            read(&["test"], "name,count\na,1\n").unwrap(),
+            json(r#"[{"name": "a", "count": "1"}]"#)
+        );
+    }
+
+    #[test]
+    fn test_no_header() {
+        assert_eq!(
+            read(&["test", "--csv-no-header"], "name,count\na,1\n").unwrap(),
+            json(r#"[["name", "count"], ["a", "1"]]"#)
+        );
+    }
+
+    #[test]
+    fn test_infer_types() {
+        assert_eq!(
+            read(
+                &["test", "--csv-infer-types"],
+                "name,count,price,sale\na,1,2.5,true\n",
+            )
+            .unwrap(),
+            json(r#"[{"name": "a", "count": 1, "price": 2.5, "sale": true}]"#)
+        );
+    }
+
+    #[test]
+    fn test_tab_delimiter() {
+        let expected = json(r#"[{"name": "a,b", "count": "1"}]"#);
+
+        assert_eq!(
+            read(&["test", "--csv-delimiter", "tab"], "name\tcount\na,b\t1\n").unwrap(),
+            expected
+        );
+        assert_eq!(
+            read(&["test", "--csv-delimiter", "\\t"], "name\tcount\na,b\t1\n").unwrap(),
+            expected
+        );
+        assert!(read(&["test", "--csv-delimiter", "ab"], "name\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_error_line() {
+        match read(&["test"], "name,count\na,1\nb\n") {
+            Err(Error::Parse { line, .. }) => assert_eq!(line, Some(3)),
+            Err(e) => panic!("expected a parse error, got {}", e),
+            Ok(_) => panic!("expected a parse error"),
+        }
+    }
+}
@@ -0,0 +1,381 @@
+extern crate clap;
+#[cfg(feature = "front-matter")]
+extern crate cryogen_plugin_front_matter;
+#[cfg(feature = "github-markdown")]
+extern crate cryogen_plugin_github_markdown;
+#[cfg(feature = "json")]
+extern crate cryogen_plugin_json;
+#[cfg(feature = "markdown")]
+extern crate cryogen_plugin_markdown;
+#[cfg(feature = "toml")]
+extern crate cryogen_plugin_toml;
+#[cfg(feature = "yaml")]
+extern crate cryogen_plugin_yaml;
+extern crate cryogen_prelude;
+extern crate glob;
+#[macro_use]
+extern crate serde_json;
+
+use clap::{Arg, ArgMatches};
+#[cfg(feature = "front-matter")]
+use cryogen_plugin_front_matter::FrontMatterPlugin;
+#[cfg(feature = "github-markdown")]
+use cryogen_plugin_github_markdown::GithubMarkdownPlugin;
+#[cfg(feature = "json")]
+use cryogen_plugin_json::JsonPlugin;
+#[cfg(feature = "markdown")]
+use cryogen_plugin_markdown::MarkdownPlugin;
+#[cfg(feature = "toml")]
+use cryogen_plugin_toml::TomlPlugin;
+#[cfg(feature = "yaml")]
+use cryogen_plugin_yaml::YamlPlugin;
+use cryogen_prelude::{CompileVariablePlugin, Error, Interpretation, Source};
+use glob::glob;
+use serde_json::Value;
+use std::{cmp::Ordering, fs::File, io::Read, path::Path, time::UNIX_EPOCH};
+
+const DIR_PLUGIN: &str = "dir-plugin";
+const DIR_SORT: &str = "dir-sort";
+const DIR_REVERSE: &str = "dir-reverse";
+
+/// The plugins that can be used to read files.
+///
+const PLUGIN_NAMES: &[&str] = &[
+    #[cfg(feature = "front-matter")]
+    "front-matter",
+    #[cfg(feature = "github-markdown")]
+    "gfm",
+    #[cfg(feature = "json")]
+    "json",
+    #[cfg(feature = "markdown")]
+    "markdown",
+    #[cfg(feature = "toml")]
+    "toml",
+    #[cfg(feature = "yaml")]
+    "yaml",
+];
+
+/// Picks a plugin to read a file with from its extension.
+///
+fn plugin_for(path: &Path) -> Option<&'static str> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        #[cfg(feature = "markdown")]
+        Some("md") | Some("markdown") => Some("markdown"),
+        #[cfg(all(feature = "github-markdown", not(feature = "markdown")))]
+        Some("md") | Some("markdown") => Some("gfm"),
+        #[cfg(feature = "json")]
+        Some("json") => Some("json"),
+        #[cfg(feature = "toml")]
+        Some("toml") => Some("toml"),
+        #[cfg(feature = "yaml")]
+        Some("yml") | Some("yaml") => Some("yaml"),
+        _ => None,
+    }
+}
+
+/// Looks up a dotted key (e.g. `date` or `metadata.date`) in a value. Keys
+/// are looked up in the front matter of documents first, so the same key
+/// sorts Markdown and data files.
+///
+fn lookup<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    let find = |value: &'a Value| {
+        key.split('.')
+            .try_fold(value, |value, part| value.get(part))
+    };
+
+    value.get("metadata").and_then(find).or_else(|| find(value))
+}
+
+/// Ranks the type of a value, so values of different types have a total
+/// order. Missing and null values are ranked last.
+///
+fn rank(value: Option<&Value>) -> u8 {
+    match value {
+        Some(&Value::Bool(_)) => 0,
+        Some(&Value::Number(_)) => 1,
+        Some(&Value::String(_)) => 2,
+        Some(&Value::Array(_)) => 3,
+        Some(&Value::Object(_)) => 4,
+        Some(&Value::Null) | None => 5,
+    }
+}
+
+/// Orders values for sorting. Values are ordered by type first (booleans,
+/// numbers, strings, arrays, then maps), and then by value. Arrays and maps
+/// are never reordered among themselves.
+///
+fn compare(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    match (a, b) {
+        (Some(Value::Number(a)), Some(Value::Number(b))) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Some(Value::String(a)), Some(Value::String(b))) => a.cmp(b),
+        (Some(&Value::Bool(a)), Some(&Value::Bool(b))) => a.cmp(&b),
+        (a, b) => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Assigns a variable to a list of every file matching a glob pattern. Each
+/// file is read with another plugin, and is exposed as an object with the
+/// `path`, `stem`, and `modified` time of the file, and the parsed `value`.
+/// The front matter of Markdown files is always read, so it can be sorted by.
+///
+pub struct DirPlugin {
+    #[cfg(feature = "front-matter")]
+    front_matter: FrontMatterPlugin,
+    #[cfg(feature = "github-markdown")]
+    github_markdown: GithubMarkdownPlugin,
+    #[cfg(feature = "json")]
+    json: JsonPlugin,
+    #[cfg(feature = "markdown")]
+    markdown: MarkdownPlugin,
+    #[cfg(feature = "toml")]
+    toml: TomlPlugin,
+    #[cfg(feature = "yaml")]
+    yaml: YamlPlugin,
+    plugin: Option<String>,
+    sort: Option<String>,
+    reverse: bool,
+}
+
+impl DirPlugin {
+    /// Reads a file with the named plugin.
+    ///
+    #[cfg_attr(
+        not(any(
+            feature = "front-matter",
+            feature = "github-markdown",
+            feature = "json",
+            feature = "markdown",
+            feature = "toml",
+            feature = "yaml"
+        )),
+        allow(unused_macros, unused_variables)
+    )]
+    fn read_file(&self, plugin: &str, file: File) -> Result<Value, Error> {
+        let src: Source<File> = Source::File(file);
+
+        macro_rules! read {
+            ($plugin:expr) => {
+                $plugin.read(src).and_then(|value| {
+                    ::serde_json::to_value(value).map_err(|e| Error::parse(Self::PLUGIN_NAME, e))
+                })
+            };
+        }
+
+        match plugin {
+            #[cfg(feature = "front-matter")]
+            "front-matter" => read!(self.front_matter),
+            #[cfg(feature = "github-markdown")]
+            "gfm" => read!(self.github_markdown),
+            #[cfg(feature = "json")]
+            "json" => read!(self.json),
+            #[cfg(feature = "markdown")]
+            "markdown" => read!(self.markdown),
+            #[cfg(feature = "toml")]
+            "toml" => read!(self.toml),
+            #[cfg(feature = "yaml")]
+            "yaml" => read!(self.yaml),
+            plugin => Err(Error::Usage(format!("unknown plugin ({})", plugin))),
+        }
+    }
+
+    /// Reads a single matched file into an entry of the list.
+    ///
+    fn read_entry(&self, path: &Path) -> Result<Value, Error> {
+        let plugin = match self.plugin {
+            Some(ref plugin) => &plugin[..],
+            None => plugin_for(path).ok_or_else(|| {
+                Error::Usage(format!(
+                    "no plugin to read ({}), use --{} to choose one",
+                    path.display(),
+                    DIR_PLUGIN
+                ))
+            })?,
+        };
+        let file =
+            File::open(path).map_err(|e| Error::from(e).with_path(path.to_string_lossy()))?;
+        let modified = file
+            .metadata()
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|modified| modified.as_secs());
+        let value = self
+            .read_file(plugin, file)
+            .map_err(|e| e.with_path(path.to_string_lossy()))?;
+
+        Ok(json!({
+            "path": path.to_string_lossy(),
+            "stem": path.file_stem().map(|stem| stem.to_string_lossy()),
+            "modified": modified,
+            "value": value,
+        }))
+    }
+}
+
+impl CompileVariablePlugin for DirPlugin {
+    type RenderValue = Vec<Value>;
+
+    const PLUGIN_NAME: &'static str = "dir";
+
+    const ARG_NAME: &'static str = "dir";
+
+    const ARG_INTERPRETATION: Interpretation = Interpretation::Raw;
+
+    const HELP: &'static str = "Assign variable to a list of every file matching a glob pattern";
+
+    #[inline]
+    fn additional_args() -> Vec<Arg<'static, 'static>> {
+        vec![
+            Arg::with_name(DIR_PLUGIN)
+                .long(DIR_PLUGIN)
+                .help("The plugin used to read matched files (defaults to one based on the extension)")
+                .takes_value(true)
+                .possible_values(PLUGIN_NAMES),
+            Arg::with_name(DIR_SORT)
+                .long(DIR_SORT)
+                .help(
+                    "Sort matched files by a dotted key in their front matter, or in their \
+                     values (e.g. date)",
+                )
+                .takes_value(true),
+            Arg::with_name(DIR_REVERSE)
+                .long(DIR_REVERSE)
+                .help("Reverse the order of matched files"),
+        ]
+    }
+
+    #[inline]
+    fn from_args<'a>(args: &'a ArgMatches<'a>) -> DirPlugin {
+        DirPlugin {
+            #[cfg(feature = "front-matter")]
+            front_matter: FrontMatterPlugin::from_args(args),
+            #[cfg(feature = "github-markdown")]
+            github_markdown: GithubMarkdownPlugin::from_args(args).yaml_metadata(true),
+            #[cfg(feature = "json")]
+            json: JsonPlugin::from_args(args),
+            #[cfg(feature = "markdown")]
+            markdown: MarkdownPlugin::from_args(args).yaml_metadata(true),
+            #[cfg(feature = "toml")]
+            toml: TomlPlugin::from_args(args),
+            #[cfg(feature = "yaml")]
+            yaml: YamlPlugin::from_args(args),
+            plugin: args.value_of(DIR_PLUGIN).map(str::to_string),
+            sort: args.value_of(DIR_SORT).map(str::to_string),
+            reverse: args.is_present(DIR_REVERSE),
+        }
+    }
+
+    fn read<'a, R>(&self, src: Source<'a, R>) -> Result<Vec<Value>, Error>
+    where
+        R: Read,
+    {
+        let pattern = src.consume()?;
+        let paths = glob(&pattern)
+            .map_err(|e| Error::Mapping(format!("invalid pattern ({}): {}", pattern, e)))?;
+        let mut entries = Vec::new();
+
+        for path in paths {
+            let path = path?;
+
+            if path.is_file() {
+                entries.push(self.read_entry(&path)?);
+            }
+        }
+
+        if let Some(ref key) = self.sort {
+            entries.sort_by(|a, b| compare(lookup(&a["value"], key), lookup(&b["value"], key)));
+        }
+
+        if self.reverse {
+            entries.reverse();
+        }
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare, lookup};
+    use serde_json::Value;
+    use std::cmp::Ordering;
+
+    #[cfg(feature = "markdown")]
+    #[test]
+    fn test_sort_markdown_by_front_matter() {
+        use super::DirPlugin;
+        use clap::App;
+        use cryogen_prelude::{CompileVariablePlugin, Source};
+        use std::{env, fs, io::Empty};
+
+        let dir = env::temp_dir().join(format!("cryogen-dir-{}", ::std::process::id()));
+        let posts = [
+            ("b", "2018-01-03"),
+            ("a", "2018-01-01"),
+            ("c", "2018-01-02"),
+        ];
+
+        fs::create_dir_all(&dir).unwrap();
+
+        for &(stem, date) in &posts {
+            fs::write(
+                dir.join(format!("{}.md", stem)),
+                format!("---\ndate: {}\n---\n\n# {}\n", date, stem),
+            )
+            .unwrap();
+        }
+
+        let args = App::new("test")
+            .args(&DirPlugin::additional_args())
+            .get_matches_from(vec!["test", "--dir-sort", "date"]);
+        let pattern = dir.join("*.md").to_string_lossy().into_owned();
+        let src: Source<Empty> = Source::Raw(&pattern, 0);
+        let entries = DirPlugin::from_args(&args).read(src);
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        let entries = entries.unwrap();
+        let stems: Vec<&Value> = entries.iter().map(|entry| &entry["stem"]).collect();
+
+        assert_eq!(stems, vec![&json!("a"), &json!("c"), &json!("b")]);
+        assert!(!entries[0]["value"]["html"]
+            .as_str()
+            .unwrap()
+            .contains("date:"));
+    }
+
+    #[test]
+    fn test_mixed_types_have_a_total_order() {
+        let values: Vec<Value> = vec![json!("b"), json!(1), json!(null), json!("a"), json!(true)];
+        let mut sorted = values.clone();
+
+        sorted.sort_by(|a, b| compare(Some(a), Some(b)));
+
+        assert_eq!(
+            sorted,
+            vec![json!(true), json!(1), json!("a"), json!("b"), json!(null)]
+        );
+        assert_eq!(
+            compare(Some(&json!("a")), Some(&json!(1))),
+            Ordering::Greater
+        );
+        assert_eq!(compare(None, Some(&json!(1))), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_front_matter_is_looked_up_first() {
+        let markdown = json!({"metadata": {"date": "2018-01-02"}, "html": ""});
+        let data = json!({"date": "2018-01-01", "author": {"name": "ferris"}});
+
+        assert_eq!(lookup(&markdown, "date"), Some(&json!("2018-01-02")));
+        assert_eq!(
+            lookup(&markdown, "metadata.date"),
+            Some(&json!("2018-01-02"))
+        );
+        assert_eq!(lookup(&data, "date"), Some(&json!("2018-01-01")));
+        assert_eq!(lookup(&data, "author.name"), Some(&json!("ferris")));
+    }
+}
@@ -0,0 +1,155 @@
+extern crate clap;
+extern crate cryogen_prelude;
+
+use clap::{Arg, ArgMatches};
+use cryogen_prelude::{CompileVariablePlugin, Error, Interpretation, Source};
+use std::{
+    collections::BTreeMap,
+    env::{self, VarError},
+    io::Read,
+};
+
+/// Separates the name of an environment variable from its default value.
+///
+const DEFAULT_SEPARATOR: char = '=';
+
+/// Assigns a variable to the value of an environment variable. A default
+/// can be supplied with `<ENV_NAME>=<default>`, which is used if the
+/// environment variable isn't set.
+///
+pub struct EnvPlugin;
+
+impl CompileVariablePlugin for EnvPlugin {
+    type RenderValue = String;
+
+    const PLUGIN_NAME: &'static str = "env";
+
+    const ARG_NAME: &'static str = "env";
+
+    const ARG_INTERPRETATION: Interpretation = Interpretation::Raw;
+
+    const HELP: &'static str =
+        "Assign variable to value of an environment variable (use ENV_NAME=default for a default)";
+
+    #[inline]
+    fn additional_args() -> Vec<Arg<'static, 'static>> {
+        vec![]
+    }
+
+    #[inline]
+    fn from_args<'a>(_: &'a ArgMatches<'a>) -> EnvPlugin {
+        EnvPlugin
+    }
+
+    fn read<'a, R>(&self, src: Source<'a, R>) -> Result<String, Error>
+    where
+        R: Read,
+    {
+        let data = src.consume()?;
+        let mut splits = data.splitn(2, DEFAULT_SEPARATOR);
+        let name = splits.next().unwrap_or("");
+        let default = splits.next();
+
+        match env::var(name) {
+            Ok(value) => Ok(value),
+            Err(VarError::NotPresent) => default.map(str::to_string).ok_or_else(|| {
+                Error::Mapping(format!("environment variable ({}) is not set", name))
+            }),
+            Err(VarError::NotUnicode(_)) => Err(Error::Mapping(format!(
+                "environment variable ({}) is not valid unicode",
+                name
+            ))),
+        }
+    }
+}
+
+/// Assigns a variable to a map of every environment variable that starts
+/// with a prefix. The prefix is removed from the keys of the map. Without a
+/// variable name (e.g. `--env-prefix CRYOGEN_`), the map is bound to `env`.
+///
+pub struct EnvPrefixPlugin;
+
+impl CompileVariablePlugin for EnvPrefixPlugin {
+    type RenderValue = BTreeMap<String, String>;
+
+    const PLUGIN_NAME: &'static str = "env-prefix";
+
+    const ARG_NAME: &'static str = "env-prefix";
+
+    const ARG_INTERPRETATION: Interpretation = Interpretation::Raw;
+
+    const DEFAULT_VAR_NAME: Option<&'static str> = Some("env");
+
+    const HELP: &'static str =
+        "Assign variable to a map of environment variables starting with a prefix (e.g. \
+         CRYOGEN_ binds env, or ci:CI_ binds ci)";
+
+    #[inline]
+    fn additional_args() -> Vec<Arg<'static, 'static>> {
+        vec![]
+    }
+
+    #[inline]
+    fn from_args<'a>(_: &'a ArgMatches<'a>) -> EnvPrefixPlugin {
+        EnvPrefixPlugin
+    }
+
+    fn read<'a, R>(&self, src: Source<'a, R>) -> Result<Self::RenderValue, Error>
+    where
+        R: Read,
+    {
+        let prefix = src.consume()?;
+
+        Ok(env::vars()
+            .filter(|(name, _)| name.starts_with(&prefix[..]))
+            .map(|(name, value)| (name[prefix.len()..].to_string(), value))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EnvPlugin, EnvPrefixPlugin};
+    use cryogen_prelude::{CompileVariablePlugin, Source};
+    use std::{env, io::Empty};
+
+    fn read_env(mapping: &str) -> Result<String, String> {
+        let src: Source<Empty> = Source::Raw(mapping, 0);
+
+        EnvPlugin.read(src).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_env() {
+        env::set_var("CRYOGEN_TEST_SHA", "abc123");
+
+        assert_eq!(read_env("CRYOGEN_TEST_SHA"), Ok("abc123".into()));
+        assert_eq!(read_env("CRYOGEN_TEST_SHA=unknown"), Ok("abc123".into()));
+    }
+
+    #[test]
+    fn test_missing_env() {
+        assert_eq!(
+            read_env("CRYOGEN_TEST_MISSING"),
+            Err("environment variable (CRYOGEN_TEST_MISSING) is not set".into())
+        );
+        assert_eq!(
+            read_env("CRYOGEN_TEST_MISSING=production"),
+            Ok("production".into())
+        );
+        assert_eq!(read_env("CRYOGEN_TEST_MISSING="), Ok("".into()));
+    }
+
+    #[test]
+    fn test_prefix_is_stripped() {
+        env::set_var("CRYOGEN_PREFIX_TEST_BUILD", "42");
+        env::set_var("CRYOGEN_PREFIX_TEST_DEPLOY", "staging");
+
+        let src: Source<Empty> = Source::Raw("CRYOGEN_PREFIX_TEST_", 0);
+        let vars = EnvPrefixPlugin.read(src).unwrap();
+
+        assert_eq!(vars.len(), 2);
+        assert_eq!(vars["BUILD"], "42");
+        assert_eq!(vars["DEPLOY"], "staging");
+    }
+}
@@ -0,0 +1,196 @@
+extern crate clap;
+extern crate cryogen_plugin_toml;
+extern crate cryogen_prelude;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate serde_yaml;
+extern crate toml;
+
+use clap::{Arg, ArgMatches};
+use cryogen_plugin_toml::convert_datetimes;
+use cryogen_prelude::{
+    markdown::{read_block, read_header, MarkdownMetadata},
+    CompileVariablePlugin, Error, Interpretation, Source,
+};
+use serde_json::Deserializer;
+use std::{io::Read, str};
+
+const PLUGIN_NAME: &str = "front-matter";
+
+const TOML_DIVIDER: &[u8] = b"+++";
+
+/// Value written to Tera context.
+///
+#[derive(Serialize)]
+pub struct FrontMatter {
+    metadata: Option<MarkdownMetadata>,
+    body: String,
+    offset: usize,
+}
+
+/// Tries to read a TOML block delimited by `+++` from the beginning of an
+/// input buffer.
+///
+fn read_toml_header(buf: &[u8]) -> Result<(Option<MarkdownMetadata>, usize), Error> {
+    match read_block(buf, TOML_DIVIDER) {
+        Some((block, total_read)) => {
+            let block = str::from_utf8(block).map_err(|e| Error::parse(PLUGIN_NAME, e))?;
+            let value = block
+                .parse::<toml::Value>()
+                .map_err(|e| match e.line_col() {
+                    // Lines and columns start at 0, and the block starts on the
+                    // line after the opening divider.
+                    //
+                    Some((line, column)) => Error::parse(PLUGIN_NAME, &e).at(line + 2, column + 1),
+                    None => Error::parse(PLUGIN_NAME, &e),
+                })?;
+            let meta = serde_yaml::to_value(convert_datetimes(value))
+                .map_err(|e| Error::parse(PLUGIN_NAME, e))?;
+
+            Ok((Some(meta), total_read))
+        }
+        None => Ok((None, 0)),
+    }
+}
+
+/// Tries to read a JSON object from the beginning of an input buffer. If
+/// the buffer doesn't start with an object (e.g. it starts with a
+/// `{{< shortcode >}}`), it has no front matter.
+///
+fn read_json_header(buf: &[u8]) -> Result<(Option<MarkdownMetadata>, usize), Error> {
+    let mut stream = Deserializer::from_slice(buf).into_iter::<serde_json::Value>();
+    let meta = match stream.next() {
+        Some(Ok(meta @ serde_json::Value::Object(_))) => {
+            serde_yaml::to_value(meta).map_err(|e| Error::parse(PLUGIN_NAME, e))?
+        }
+        _ => return Ok((None, 0)),
+    };
+    let mut total_read = stream.byte_offset();
+
+    if buf[total_read..].starts_with(b"\r\n") {
+        total_read += 2;
+    } else if buf[total_read..].starts_with(b"\n") {
+        total_read += 1;
+    }
+
+    Ok((Some(meta), total_read))
+}
+
+/// Reads only the front matter of a file, without rendering the rest of it.
+/// YAML (`---`), TOML (`+++`), and JSON front matter are supported.
+///
+pub struct FrontMatterPlugin;
+
+impl CompileVariablePlugin for FrontMatterPlugin {
+    type RenderValue = FrontMatter;
+
+    const PLUGIN_NAME: &'static str = PLUGIN_NAME;
+
+    const ARG_NAME: &'static str = "front-matter";
+
+    const ARG_INTERPRETATION: Interpretation = Interpretation::Path;
+
+    const SCHEMA_POINTER: &'static str = "/metadata";
+
+    const HELP: &'static str = "Assign variable to the front matter and raw body of a file";
+
+    #[inline]
+    fn additional_args() -> Vec<Arg<'static, 'static>> {
+        vec![]
+    }
+
+    #[inline]
+    fn from_args<'a>(_: &'a ArgMatches<'a>) -> FrontMatterPlugin {
+        FrontMatterPlugin
+    }
+
+    fn read<'a, R>(&self, src: Source<'a, R>) -> Result<FrontMatter, Error>
+    where
+        R: Read,
+    {
+        let data = src.consume()?;
+        let header = if data.starts_with('{') {
+            read_json_header(data.as_bytes())
+        } else if data.starts_with("+++") {
+            read_toml_header(data.as_bytes())
+        } else {
+            read_header(PLUGIN_NAME, data.as_bytes())
+        };
+        let (metadata, offset) = header?;
+
+        Ok(FrontMatter {
+            metadata,
+            body: data[offset..].to_string(),
+            offset,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FrontMatter, FrontMatterPlugin};
+    use cryogen_prelude::{CompileVariablePlugin, Error, Source};
+    use std::io::Empty;
+
+    fn read(data: &str) -> Result<FrontMatter, Error> {
+        let src: Source<Empty> = Source::Raw(data, 0);
+
+        FrontMatterPlugin.read(src)
+    }
+
+    fn title(front_matter: &FrontMatter) -> Option<&str> {
+        front_matter
+            .metadata
+            .as_ref()
+            .and_then(|meta| meta.get("title"))
+            .and_then(|title| title.as_str())
+    }
+
+    #[test]
+    fn test_yaml_header() {
+        let front_matter = read("---\ntitle: Hello\n---\nBody\n").unwrap();
+
+        assert_eq!(title(&front_matter), Some("Hello"));
+        assert_eq!(front_matter.body, "Body\n");
+        assert_eq!(front_matter.offset, 21);
+    }
+
+    #[test]
+    fn test_toml_header() {
+        let front_matter = read("+++\ntitle = \"Hello\"\n+++\nBody\n").unwrap();
+
+        assert_eq!(title(&front_matter), Some("Hello"));
+        assert_eq!(front_matter.body, "Body\n");
+        assert_eq!(front_matter.offset, 24);
+    }
+
+    #[test]
+    fn test_json_header() {
+        let front_matter = read("{\"title\": \"Hello\"}\nBody\n").unwrap();
+
+        assert_eq!(title(&front_matter), Some("Hello"));
+        assert_eq!(front_matter.body, "Body\n");
+        assert_eq!(front_matter.offset, 19);
+    }
+
+    #[test]
+    fn test_body_starting_with_a_brace() {
+        let data = "{{< figure src=\"a.png\" >}}\nBody\n";
+        let front_matter = read(data).unwrap();
+
+        assert!(front_matter.metadata.is_none());
+        assert_eq!(front_matter.body, data);
+        assert_eq!(front_matter.offset, 0);
+    }
+
+    #[test]
+    fn test_toml_error_line() {
+        match read("+++\ntitle = \"Hello\"\ndate = \n+++\nBody\n") {
+            Err(Error::Parse { line, .. }) => assert_eq!(line, Some(3)),
+            Err(e) => panic!("expected a parse error, got {}", e),
+            Ok(_) => panic!("expected a parse error"),
+        }
+    }
+}
@@ -2,25 +2,59 @@ extern crate clap;
 extern crate comrak;
 #[macro_use]
 extern crate cryogen_prelude;
+extern crate tera;
 
 use clap::{Arg, ArgMatches};
 use comrak::{markdown_to_html, ComrakOptions};
-use cryogen_prelude::{CompileVariablePlugin, Interpretation, Source,
-                      markdown::{read_header, RenderedMarkdown}};
-use std::io::Read;
-
-const GFM_YAML_METADATA: &'static str = "gfm-yaml-metadata";
-const GFM_HARDBREAKS: &'static str = "gfm-hardbreaks";
-const GFM_SMART_PUNCTUATION: &'static str = "gfm-smart-puncutation";
-const GFM_PRE_LANG: &'static str = "gfm-pre-lang";
-const GFM_SAFE: &'static str = "gfm-safe";
-const GFM_STRIKETHROUGH: &'static str = "gfm-strikethrough";
-const GFM_TAG_FILTER: &'static str = "gfm-tag-filter";
-const GFM_TABLES: &'static str = "gfm-tables";
-const GFM_AUTOLINK: &'static str = "gfm-autolink";
-const GFM_TASKLISTS: &'static str = "gfm-tasklists";
-const GFM_SUPERSCRIPT: &'static str = "gfm-superscript";
-const GFM_FOOTNOTES: &'static str = "gfm-footnotes";
+use cryogen_prelude::highlight;
+#[cfg(feature = "highlight")]
+use cryogen_prelude::highlight::Highlighter;
+use cryogen_prelude::{
+    markdown::{read_header, render_filter, RenderedMarkdown},
+    CompileVariablePlugin, Error, Interpretation, Source,
+};
+use std::{
+    collections::HashMap,
+    io::Read,
+    sync::{PoisonError, RwLock},
+};
+use tera::{Tera, Value};
+
+const GFM_YAML_METADATA: &str = "gfm-yaml-metadata";
+const GFM_HARDBREAKS: &str = "gfm-hardbreaks";
+const GFM_SMART_PUNCTUATION: &str = "gfm-smart-puncutation";
+const GFM_PRE_LANG: &str = "gfm-pre-lang";
+const GFM_SAFE: &str = "gfm-safe";
+const GFM_STRIKETHROUGH: &str = "gfm-strikethrough";
+const GFM_TAG_FILTER: &str = "gfm-tag-filter";
+const GFM_TABLES: &str = "gfm-tables";
+const GFM_AUTOLINK: &str = "gfm-autolink";
+const GFM_TASKLISTS: &str = "gfm-tasklists";
+const GFM_SUPERSCRIPT: &str = "gfm-superscript";
+const GFM_FOOTNOTES: &str = "gfm-footnotes";
+const GFM_HEADING_IDS: &str = "gfm-heading-ids";
+const GFM_HIGHLIGHT: &str = "gfm-highlight";
+const GFM_HIGHLIGHT_THEME: &str = "gfm-highlight-theme";
+
+/// Options used by the `gfm` filter. Filters can't capture state in Tera
+/// 0.11, so these are global, and are replaced every time the filter is
+/// registered for a new Tera instance. Only one set of options can be in
+/// use at a time.
+///
+static FILTER_OPTIONS: RwLock<Option<ComrakOptions>> = RwLock::new(None);
+
+/// Tera filter that renders a GitHub-Flavored Markdown string to HTML, with
+/// the options the plugin was built from (e.g. `{{ item.desc | gfm }}`).
+///
+fn gfm_filter(value: Value, args: HashMap<String, Value>) -> tera::Result<Value> {
+    let opts = FILTER_OPTIONS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+        .unwrap_or_default();
+
+    render_filter("gfm", value, &args, |data| markdown_to_html(data, &opts))
+}
 
 pub struct GithubMarkdownPlugin {
     yaml_metadata: bool,
@@ -35,6 +69,39 @@ pub struct GithubMarkdownPlugin {
     tasklists: bool,
     superscript: bool,
     footnotes: bool,
+    heading_ids: bool,
+    #[cfg(feature = "highlight")]
+    highlighter: Option<Highlighter>,
+}
+
+impl GithubMarkdownPlugin {
+    /// Enables or disables reading the YAML metadata block, regardless of
+    /// the command line arguments the plugin was built from.
+    ///
+    #[inline]
+    pub fn yaml_metadata(mut self, enabled: bool) -> GithubMarkdownPlugin {
+        self.yaml_metadata = enabled;
+        self
+    }
+
+    /// Returns the Comrak options enabled by the command line arguments.
+    ///
+    fn options(&self) -> ComrakOptions {
+        ComrakOptions {
+            hardbreaks: self.hardbreaks,
+            smart: self.smart_punctuation,
+            github_pre_lang: self.github_pre_lang,
+            safe: self.safe,
+            ext_strikethrough: self.strikethrough,
+            ext_tagfilter: self.tag_filter,
+            ext_table: self.tables,
+            ext_autolink: self.autolink,
+            ext_tasklist: self.tasklists,
+            ext_superscript: self.superscript,
+            ext_footnotes: self.footnotes,
+            ..ComrakOptions::default()
+        }
+    }
 }
 
 impl CompileVariablePlugin for GithubMarkdownPlugin {
@@ -46,11 +113,15 @@ impl CompileVariablePlugin for GithubMarkdownPlugin {
 
     const ARG_INTERPRETATION: Interpretation = Interpretation::Path;
 
+    const SCHEMA_POINTER: &'static str = "/metadata";
+
+    const SCHEMA_REQUIRES: Option<&'static str> = Some(GFM_YAML_METADATA);
+
     const HELP: &'static str = "Assign variable to contents of a GitHub-Flavored Markdown file";
 
     #[inline]
     fn additional_args() -> Vec<Arg<'static, 'static>> {
-        args! {
+        let args = args! {
             GFM_YAML_METADATA       ["Enable YAML metadata block"];
             GFM_HARDBREAKS          ["Convert soft breaks to hard breaks in output"];
             GFM_SMART_PUNCTUATION   ["Convert punctuation to unicode equivalents"];
@@ -63,7 +134,10 @@ impl CompileVariablePlugin for GithubMarkdownPlugin {
             GFM_TASKLISTS           ["Enable task lists"];
             GFM_SUPERSCRIPT         ["Enable superscript syntax"];
             GFM_FOOTNOTES           ["Enable footnotes"];
-        }
+            GFM_HEADING_IDS         ["Add ids to headings, and build a table of contents"];
+        };
+
+        [args, highlight::args(GFM_HIGHLIGHT, GFM_HIGHLIGHT_THEME)].concat()
     }
 
     #[inline]
@@ -81,44 +155,84 @@ impl CompileVariablePlugin for GithubMarkdownPlugin {
             tasklists: args.is_present(GFM_TASKLISTS),
             superscript: args.is_present(GFM_SUPERSCRIPT),
             footnotes: args.is_present(GFM_FOOTNOTES),
+            heading_ids: args.is_present(GFM_HEADING_IDS),
+            #[cfg(feature = "highlight")]
+            highlighter: Highlighter::from_args(args, GFM_HIGHLIGHT, GFM_HIGHLIGHT_THEME),
         }
     }
 
-    fn read<'a, R>(&self, src: Source<'a, R>) -> Result<RenderedMarkdown, String>
+    fn read<'a, R>(&self, src: Source<'a, R>) -> Result<RenderedMarkdown, Error>
     where
         R: Read,
     {
-        let opts = ComrakOptions {
-            hardbreaks: self.hardbreaks,
-            smart: self.smart_punctuation,
-            github_pre_lang: self.github_pre_lang,
-            safe: self.safe,
-            ext_strikethrough: self.strikethrough,
-            ext_tagfilter: self.tag_filter,
-            ext_table: self.tables,
-            ext_autolink: self.autolink,
-            ext_tasklist: self.tasklists,
-            ext_superscript: self.superscript,
-            ext_footnotes: self.footnotes,
-            ..ComrakOptions::default()
-        };
-
         let data = src.consume()?;
         let mut view = &data[..];
         let metadata = if self.yaml_metadata {
-            match read_header(view.as_bytes()) {
-                Ok((metadata, md_start)) => {
-                    view = &view[md_start..];
-                    metadata
-                }
-                Err(e) => return Err(format!("failed to parse markdown metadata: {:?}", e)),
-            }
+            let (metadata, md_start) = read_header(Self::PLUGIN_NAME, view.as_bytes())?;
+
+            view = &view[md_start..];
+            metadata
         } else {
             None
         };
 
-        let html = markdown_to_html(&view, &opts);
+        let html = markdown_to_html(view, &self.options());
+
+        #[cfg(feature = "highlight")]
+        let html = match self.highlighter {
+            Some(ref highlighter) => highlighter.highlight_html(&html)?,
+            None => html,
+        };
+
+        let rendered = RenderedMarkdown::new(metadata, html.to_string());
+
+        if self.heading_ids {
+            Ok(rendered.with_heading_ids())
+        } else {
+            Ok(rendered)
+        }
+    }
+
+    #[inline]
+    fn extend_tera(&self, tera: &mut Tera) {
+        #[cfg(feature = "highlight")]
+        {
+            if let Some(ref highlighter) = self.highlighter {
+                tera.register_function("highlight_css", highlighter.css_function());
+            }
+        }
+
+        *FILTER_OPTIONS
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(self.options());
+
+        tera.register_filter("gfm", gfm_filter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GithubMarkdownPlugin;
+    use clap::App;
+    use cryogen_prelude::CompileVariablePlugin;
+    use tera::{Context, Tera};
+
+    #[test]
+    fn test_filter_options_are_replaced() {
+        let render_filter = |argv: &[&str]| {
+            let args = App::new("test")
+                .args(&GithubMarkdownPlugin::additional_args())
+                .get_matches_from(argv);
+            let mut tera = Tera::default();
+            let mut context = Context::new();
+
+            GithubMarkdownPlugin::from_args(&args).extend_tera(&mut tera);
+            tera.add_raw_template("t", "{{ s | gfm | safe }}").unwrap();
+            context.insert("s", "~~gone~~\n");
+            tera.render("t", &context).unwrap()
+        };
 
-        Ok(RenderedMarkdown::new(metadata, html.to_string()))
+        assert!(render_filter(&["test", "--gfm-strikethrough"]).contains("<del>gone</del>"));
+        assert!(!render_filter(&["test"]).contains("<del>"));
     }
 }
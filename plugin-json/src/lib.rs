@@ -1,10 +1,31 @@
 extern crate clap;
 extern crate cryogen_prelude;
 extern crate serde_json;
+extern crate tera;
 
 use clap::{Arg, ArgMatches};
-use cryogen_prelude::{CompileVariablePlugin, Interpretation, Source};
-use std::io::Read;
+use cryogen_prelude::{CompileVariablePlugin, Error, Interpretation, Source};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, Read},
+};
+use tera::{Tera, Value};
+
+/// Tera function that reads a JSON file, so templates can load data that
+/// isn't bound to a variable (e.g. `load_json(path="data/menu.json")`).
+///
+fn load_json(args: HashMap<String, Value>) -> tera::Result<Value> {
+    let path = match args.get("path") {
+        Some(Value::String(path)) => path,
+        Some(_) => return Err("`load_json` requires `path` to be a string".into()),
+        None => return Err("`load_json` requires a `path` argument".into()),
+    };
+    let file = File::open(path).map_err(|e| format!("failed to open ({}): {}", path, e))?;
+
+    serde_json::from_reader(file)
+        .map_err(|e| format!("failed to parse ({}) with json plugin: {}", path, e).into())
+}
 
 pub struct JsonPlugin;
 
@@ -17,7 +38,10 @@ impl CompileVariablePlugin for JsonPlugin {
 
     const ARG_INTERPRETATION: Interpretation = Interpretation::Path;
 
-    const HELP: &'static str = "Assign variable to contents of JSON file";
+    const SELECTORS: bool = true;
+
+    const HELP: &'static str =
+        "Assign variable to contents of JSON file, or to part of it (e.g. data.json#/products)";
 
     #[inline]
     fn additional_args() -> Vec<Arg<'static, 'static>> {
@@ -30,10 +54,58 @@ impl CompileVariablePlugin for JsonPlugin {
     }
 
     #[inline]
-    fn read<'a, R>(&self, src: Source<'a, R>) -> Result<Self::RenderValue, String>
+    fn read<'a, R>(&self, src: Source<'a, R>) -> Result<Self::RenderValue, Error>
     where
         R: Read,
     {
-        serde_json::from_reader(src).map_err(|e| e.to_string())
+        serde_json::from_reader(src).map_err(|e| {
+            if e.is_io() {
+                Error::from(io::Error::from(e))
+            } else {
+                Error::parse(Self::PLUGIN_NAME, &e).at(e.line(), e.column())
+            }
+        })
+    }
+
+    #[inline]
+    fn extend_tera(&self, tera: &mut Tera) {
+        tera.register_function("load_json", Box::new(load_json));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JsonPlugin;
+    use cryogen_prelude::{CompileVariablePlugin, Error, Source};
+    use std::io::{self, Read};
+
+    /// A reader that always fails, like reading a directory.
+    ///
+    struct Failing;
+
+    impl Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("Is a directory"))
+        }
+    }
+
+    #[test]
+    fn test_io_errors_are_not_parse_errors() {
+        match JsonPlugin.read(Source::File(Failing)) {
+            Err(Error::Io { error, .. }) => assert_eq!(error.to_string(), "Is a directory"),
+            Err(e) => panic!("expected an io error, got {}", e),
+            Ok(_) => panic!("expected an io error"),
+        }
+    }
+
+    #[test]
+    fn test_parse_error_location() {
+        let src: Source<io::Empty> = Source::Raw("{\n  \"a\": }", 0);
+
+        match JsonPlugin.read(src) {
+            Err(e @ Error::Parse { .. }) => assert!(e.to_string().starts_with("failed to parse")),
+            Err(e) => panic!("expected a parse error, got {}", e),
+            Ok(_) => panic!("expected a parse error"),
+        }
     }
 }
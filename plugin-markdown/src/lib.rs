@@ -2,21 +2,96 @@ extern crate clap;
 #[macro_use]
 extern crate cryogen_prelude;
 extern crate pulldown_cmark;
+extern crate tera;
 
 use clap::{Arg, ArgMatches};
-use cryogen_prelude::{CompileVariablePlugin, Interpretation, Source,
-                      markdown::{read_header, RenderedMarkdown}};
+use cryogen_prelude::highlight;
+#[cfg(feature = "highlight")]
+use cryogen_prelude::highlight::Highlighter;
+use cryogen_prelude::{
+    markdown::{read_header, render_filter, RenderedMarkdown},
+    CompileVariablePlugin, Error, Interpretation, Source,
+};
 use pulldown_cmark::{html, Options, Parser};
-use std::io::Read;
+use std::{
+    collections::HashMap,
+    io::Read,
+    sync::{PoisonError, RwLock},
+};
+use tera::{Tera, Value};
+
+const MD_YAML_METADATA: &str = "markdown-yaml-metadata";
+const MD_FOOTNOTES: &str = "markdown-footnotes";
+const MD_TABLES: &str = "markdown-tables";
+const MD_HEADING_IDS: &str = "markdown-heading-ids";
+const MD_HIGHLIGHT: &str = "markdown-highlight";
+const MD_HIGHLIGHT_THEME: &str = "markdown-highlight-theme";
+
+/// Options used by the `markdown` filter. Tera 0.11 filters are `fn`
+/// pointers that can't capture the plugin, so the options are shared by the
+/// whole process. They're replaced whenever the filter is registered, which
+/// the CLI does for every Tera instance it renders with, so a render always
+/// uses the options from its own arguments. Tera instances built from
+/// different arguments can't render at the same time.
+///
+static FILTER_OPTIONS: RwLock<Option<Options>> = RwLock::new(None);
+
+/// Renders Markdown to HTML.
+///
+fn render(data: &str, opts: Options) -> String {
+    let mut html = String::new();
+
+    html::push_html(&mut html, Parser::new_ext(data, opts));
+
+    html
+}
+
+/// Tera filter that renders a Markdown string to HTML, with the options the
+/// plugin was built from (e.g. `{{ item.desc | markdown(inline=true) }}`).
+///
+fn markdown_filter(value: Value, args: HashMap<String, Value>) -> tera::Result<Value> {
+    let opts = FILTER_OPTIONS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .unwrap_or_else(Options::empty);
 
-const MD_YAML_METADATA: &'static str = "markdown-yaml-metadata";
-const MD_FOOTNOTES: &'static str = "markdown-footnotes";
-const MD_TABLES: &'static str = "markdown-tables";
+    render_filter("markdown", value, &args, |data| render(data, opts))
+}
 
 pub struct MarkdownPlugin {
     yaml_metadata: bool,
     footnotes: bool,
     tables: bool,
+    heading_ids: bool,
+    #[cfg(feature = "highlight")]
+    highlighter: Option<Highlighter>,
+}
+
+impl MarkdownPlugin {
+    /// Enables or disables reading the YAML metadata block, regardless of
+    /// the command line arguments the plugin was built from.
+    ///
+    #[inline]
+    pub fn yaml_metadata(mut self, enabled: bool) -> MarkdownPlugin {
+        self.yaml_metadata = enabled;
+        self
+    }
+
+    /// Returns the parser options enabled by the command line arguments.
+    ///
+    fn options(&self) -> Options {
+        let mut opts = Options::empty();
+
+        if self.footnotes {
+            opts.insert(pulldown_cmark::OPTION_ENABLE_FOOTNOTES);
+        }
+
+        if self.tables {
+            opts.insert(pulldown_cmark::OPTION_ENABLE_TABLES);
+        }
+
+        opts
+    }
 }
 
 impl CompileVariablePlugin for MarkdownPlugin {
@@ -28,59 +103,132 @@ impl CompileVariablePlugin for MarkdownPlugin {
 
     const ARG_INTERPRETATION: Interpretation = Interpretation::Path;
 
+    const SCHEMA_POINTER: &'static str = "/metadata";
+
+    const SCHEMA_REQUIRES: Option<&'static str> = Some(MD_YAML_METADATA);
+
     const HELP: &'static str = "Assign variable to contents of a Markdown file";
 
     #[inline]
     fn additional_args() -> Vec<Arg<'static, 'static>> {
-        args! {
+        let args = args! {
             MD_YAML_METADATA ["Enable YAML metadata block"];
             MD_FOOTNOTES     ["Enable footnotes"];
             MD_TABLES        ["Enable tables"];
-        }
+            MD_HEADING_IDS   ["Add ids to headings, and build a table of contents"];
+        };
+
+        [args, highlight::args(MD_HIGHLIGHT, MD_HIGHLIGHT_THEME)].concat()
     }
 
     #[inline]
     fn from_args<'a>(args: &'a ArgMatches<'a>) -> MarkdownPlugin {
         MarkdownPlugin {
             yaml_metadata: args.is_present(MD_YAML_METADATA),
-            footnotes: args.is_present(MD_YAML_METADATA),
+            footnotes: args.is_present(MD_FOOTNOTES),
             tables: args.is_present(MD_TABLES),
+            heading_ids: args.is_present(MD_HEADING_IDS),
+            #[cfg(feature = "highlight")]
+            highlighter: Highlighter::from_args(args, MD_HIGHLIGHT, MD_HIGHLIGHT_THEME),
         }
     }
 
-    fn read<'a, R>(&self, src: Source<'a, R>) -> Result<RenderedMarkdown, String>
+    fn read<'a, R>(&self, src: Source<'a, R>) -> Result<RenderedMarkdown, Error>
     where
         R: Read,
     {
-        let mut opts = Options::empty();
-
-        if self.footnotes {
-            opts.insert(pulldown_cmark::OPTION_ENABLE_FOOTNOTES);
-        }
-
-        if self.tables {
-            opts.insert(pulldown_cmark::OPTION_ENABLE_TABLES);
-        }
-
         let data = src.consume()?;
         let mut view = &data[..];
         let metadata = if self.yaml_metadata {
-            match read_header(view.as_bytes()) {
-                Ok((metadata, md_start)) => {
-                    view = &view[md_start..];
-                    metadata
-                }
-                Err(e) => return Err(format!("failed to parse markdown metadata: {:?}", e)),
-            }
+            let (metadata, md_start) = read_header(Self::PLUGIN_NAME, view.as_bytes())?;
+
+            view = &view[md_start..];
+            metadata
         } else {
             None
         };
 
-        let mut html = String::new();
-        let parser = Parser::new_ext(&view[..], opts);
+        let html = render(view, self.options());
+
+        #[cfg(feature = "highlight")]
+        let html = match self.highlighter {
+            Some(ref highlighter) => highlighter.highlight_html(&html)?,
+            None => html,
+        };
+
+        let rendered = RenderedMarkdown::new(metadata, html);
+
+        if self.heading_ids {
+            Ok(rendered.with_heading_ids())
+        } else {
+            Ok(rendered)
+        }
+    }
+
+    #[inline]
+    fn extend_tera(&self, tera: &mut Tera) {
+        #[cfg(feature = "highlight")]
+        {
+            if let Some(ref highlighter) = self.highlighter {
+                tera.register_function("highlight_css", highlighter.css_function());
+            }
+        }
+
+        // Options from an earlier registration are always replaced, even if
+        // a render panicked while holding the lock.
+        //
+        *FILTER_OPTIONS
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(self.options());
+
+        tera.register_filter("markdown", markdown_filter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MarkdownPlugin;
+    use clap::App;
+    use cryogen_prelude::{CompileVariablePlugin, Source};
+    use std::io::Empty;
+    use tera::{Context, Tera};
+
+    fn render(argv: &[&str]) -> String {
+        let args = App::new("test")
+            .args(&MarkdownPlugin::additional_args())
+            .get_matches_from(argv);
+        let src: Source<Empty> = Source::Raw("Text[^1]\n\n[^1]: A note\n", 0);
+
+        MarkdownPlugin::from_args(&args)
+            .read(src)
+            .unwrap()
+            .html()
+            .to_string()
+    }
 
-        html::push_html(&mut html, parser);
+    #[test]
+    fn test_footnotes_flag() {
+        assert!(render(&["test", "--markdown-footnotes"]).contains("footnote-definition"));
+        assert!(!render(&["test", "--markdown-yaml-metadata"]).contains("footnote-definition"));
+    }
+
+    #[test]
+    fn test_filter_options_are_replaced() {
+        let render_filter = |argv: &[&str]| {
+            let args = App::new("test")
+                .args(&MarkdownPlugin::additional_args())
+                .get_matches_from(argv);
+            let mut tera = Tera::default();
+            let mut context = Context::new();
+
+            MarkdownPlugin::from_args(&args).extend_tera(&mut tera);
+            tera.add_raw_template("t", "{{ s | markdown | safe }}")
+                .unwrap();
+            context.insert("s", "Text[^1]\n\n[^1]: A note\n");
+            tera.render("t", &context).unwrap()
+        };
 
-        Ok(RenderedMarkdown::new(metadata, html.to_string()))
+        assert!(render_filter(&["test", "--markdown-footnotes"]).contains("footnote-definition"));
+        assert!(!render_filter(&["test"]).contains("footnote-definition"));
     }
 }
@@ -2,7 +2,7 @@ extern crate clap;
 extern crate cryogen_prelude;
 
 use clap::{Arg, ArgMatches};
-use cryogen_prelude::{CompileVariablePlugin, Interpretation, Source};
+use cryogen_prelude::{CompileVariablePlugin, Error, Interpretation, Source};
 use std::{io::Read, str::FromStr};
 
 /// Creates a plugin to input a assign a primitive type to a variable.
@@ -40,16 +40,16 @@ macro_rules! primitive_plugin {
             }
 
             #[inline]
-            fn read<'a, R>(&self, src: Source<'a, R>) -> Result<Self::RenderValue, String>
+            fn read<'a, R>(&self, src: Source<'a, R>) -> Result<Self::RenderValue, Error>
             where
-                R: Read
+                R: Read,
             {
                 let $bind = src.consume()?;
                 let $bind = &$bind[..];
                 $eval
             }
         }
-    }
+    };
 }
 
 primitive_plugin! {
@@ -67,7 +67,7 @@ primitive_plugin! {
     PluginName = "int";
     ArgName = "int";
     Help = "Assign variable to integer value";
-    (val) => isize::from_str(val).map_err(|e| format!("error parsing int: {:?}", e));
+    (val) => isize::from_str(val).map_err(|e| Error::parse("int", e));
 }
 
 primitive_plugin! {
@@ -76,7 +76,7 @@ primitive_plugin! {
     PluginName = "float";
     ArgName = "float";
     Help = "Assign variable to float value";
-    (val) => f64::from_str(val).map_err(|e| format!("error parsing float: {:?}", e));
+    (val) => f64::from_str(val).map_err(|e| Error::parse("float", e));
 }
 
 primitive_plugin! {
@@ -85,5 +85,5 @@ primitive_plugin! {
     PluginName = "bool";
     ArgName = "bool";
     Help = "Assign variable to boolean value";
-    (val) => bool::from_str(val).map_err(|e| format!("error parsing boolean: {:?}", e));
+    (val) => bool::from_str(val).map_err(|e| Error::parse("bool", e));
 }
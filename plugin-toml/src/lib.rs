@@ -0,0 +1,124 @@
+extern crate clap;
+extern crate cryogen_prelude;
+extern crate toml;
+
+use clap::{Arg, ArgMatches};
+use cryogen_prelude::{CompileVariablePlugin, Error, Interpretation, Source};
+use std::io::Read;
+use toml::Value;
+
+/// Replaces every datetime in a TOML value with its TOML string form. Only
+/// offset datetimes are RFC 3339 (e.g. `1979-05-27T07:32:00Z`). Local
+/// datetimes, dates, and times keep their offset-free form (e.g.
+/// `1979-05-27T07:32:00`, `1979-05-27`, or `07:32:00`). Tera's `date` filter
+/// can format all of them except local times.
+///
+pub fn convert_datetimes(value: Value) -> Value {
+    match value {
+        Value::Datetime(datetime) => Value::String(datetime.to_string()),
+        Value::Array(values) => Value::Array(values.into_iter().map(convert_datetimes).collect()),
+        Value::Table(table) => Value::Table(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, convert_datetimes(value)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// Converts a TOML parse error, whose lines and columns start at 0.
+///
+fn parse_error(plugin: &'static str, e: &toml::de::Error) -> Error {
+    match e.line_col() {
+        Some((line, column)) => Error::parse(plugin, e).at(line + 1, column + 1),
+        None => Error::parse(plugin, e),
+    }
+}
+
+pub struct TomlPlugin;
+
+impl CompileVariablePlugin for TomlPlugin {
+    type RenderValue = Value;
+
+    const PLUGIN_NAME: &'static str = "toml";
+
+    const ARG_NAME: &'static str = "toml";
+
+    const ARG_INTERPRETATION: Interpretation = Interpretation::Path;
+
+    const HELP: &'static str = "Assign variable to contents of TOML file";
+
+    #[inline]
+    fn additional_args() -> Vec<Arg<'static, 'static>> {
+        vec![]
+    }
+
+    #[inline]
+    fn from_args<'a>(_: &'a ArgMatches<'a>) -> TomlPlugin {
+        TomlPlugin
+    }
+
+    #[inline]
+    fn read<'a, R>(&self, src: Source<'a, R>) -> Result<Self::RenderValue, Error>
+    where
+        R: Read,
+    {
+        let data = src.consume()?;
+
+        data.parse::<Value>()
+            .map(convert_datetimes)
+            .map_err(|e| parse_error(Self::PLUGIN_NAME, &e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{convert_datetimes, TomlPlugin};
+    use cryogen_prelude::{CompileVariablePlugin, Error, Source};
+    use std::io;
+    use toml::Value;
+
+    fn read(data: &str) -> Result<Value, Error> {
+        let src: Source<io::Empty> = Source::Raw(data, 0);
+
+        TomlPlugin.read(src)
+    }
+
+    #[test]
+    fn test_convert_datetimes() {
+        let value: Value = "offset = 1979-05-27T07:32:00Z\n\
+                            local = 1979-05-27T07:32:00\n\
+                            [nested]\n\
+                            dates = [1979-05-27, 07:32:00]"
+            .parse()
+            .unwrap();
+        let expected: Value = "offset = \"1979-05-27T07:32:00Z\"\n\
+                               local = \"1979-05-27T07:32:00\"\n\
+                               [nested]\n\
+                               dates = [\"1979-05-27\", \"07:32:00\"]"
+            .parse()
+            .unwrap();
+
+        assert_eq!(convert_datetimes(value), expected);
+    }
+
+    #[test]
+    fn test_read() {
+        let value = read("title = \"Hello\"\ndate = 2018-01-01").unwrap();
+
+        assert_eq!(value["title"].as_str(), Some("Hello"));
+        assert_eq!(value["date"].as_str(), Some("2018-01-01"));
+    }
+
+    #[test]
+    fn test_parse_error_location() {
+        match read("title = \"Hello\"\ndate = ") {
+            Err(Error::Parse { line, column, .. }) => {
+                assert_eq!((line, column), (Some(2), Some(8)))
+            }
+            Err(e) => panic!("expected a parse error, got {}", e),
+            Ok(_) => panic!("expected a parse error"),
+        }
+    }
+}
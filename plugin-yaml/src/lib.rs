@@ -3,7 +3,7 @@ extern crate cryogen_prelude;
 extern crate serde_yaml;
 
 use clap::{Arg, ArgMatches};
-use cryogen_prelude::{CompileVariablePlugin, Interpretation, Source};
+use cryogen_prelude::{CompileVariablePlugin, Error, Interpretation, Source};
 use std::io::Read;
 
 pub struct YamlPlugin;
@@ -17,7 +17,10 @@ impl CompileVariablePlugin for YamlPlugin {
 
     const ARG_INTERPRETATION: Interpretation = Interpretation::Path;
 
-    const HELP: &'static str = "Assign variable to contents of YAML file";
+    const SELECTORS: bool = true;
+
+    const HELP: &'static str =
+        "Assign variable to contents of YAML file, or to part of it (e.g. data.yml#/products)";
 
     #[inline]
     fn additional_args() -> Vec<Arg<'static, 'static>> {
@@ -30,10 +33,54 @@ impl CompileVariablePlugin for YamlPlugin {
     }
 
     #[inline]
-    fn read<'a, R>(&self, src: Source<'a, R>) -> Result<Self::RenderValue, String>
+    fn read<'a, R>(&self, src: Source<'a, R>) -> Result<Self::RenderValue, Error>
     where
         R: Read,
     {
-        serde_yaml::from_reader(src).map_err(|e| e.to_string())
+        let data = src.consume()?;
+
+        serde_yaml::from_str(&data).map_err(|e| match e.location() {
+            Some(location) => {
+                Error::parse(Self::PLUGIN_NAME, &e).at(location.line(), location.column())
+            }
+            None => Error::parse(Self::PLUGIN_NAME, &e),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::YamlPlugin;
+    use cryogen_prelude::{CompileVariablePlugin, Error, Source};
+    use std::io::{self, Read};
+
+    /// A reader that always fails, like reading a directory.
+    ///
+    struct Failing;
+
+    impl Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("Is a directory"))
+        }
+    }
+
+    #[test]
+    fn test_io_errors_are_not_parse_errors() {
+        match YamlPlugin.read(Source::File(Failing)) {
+            Err(Error::Io { error, .. }) => assert_eq!(error.to_string(), "Is a directory"),
+            Err(e) => panic!("expected an io error, got {}", e),
+            Ok(_) => panic!("expected an io error"),
+        }
+    }
+
+    #[test]
+    fn test_parse_error_location() {
+        let src: Source<io::Empty> = Source::Raw("title: a\n  - b: [\n", 0);
+
+        match YamlPlugin.read(src) {
+            Err(e @ Error::Parse { .. }) => assert!(e.to_string().starts_with("failed to parse")),
+            Err(e) => panic!("expected a parse error, got {}", e),
+            Ok(_) => panic!("expected a parse error"),
+        }
     }
 }
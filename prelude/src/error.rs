@@ -0,0 +1,249 @@
+#[cfg(feature = "glob")]
+use glob::GlobError;
+use std::{error, fmt, io};
+
+/// An error that occurred while reading data, or rendering a template.
+///
+#[derive(Debug)]
+pub enum Error {
+    /// The arguments or config supplied are invalid.
+    ///
+    Usage(String),
+
+    /// A variable mapping couldn't be unpacked, or refers to something that
+    /// doesn't exist.
+    ///
+    Mapping(String),
+
+    /// A file couldn't be read or written.
+    ///
+    Io {
+        path: Option<String>,
+        error: io::Error,
+    },
+
+    /// A plugin couldn't parse the data it read. Lines and columns start
+    /// at 1, and the column may be unknown even if the line is known.
+    ///
+    Parse {
+        plugin: &'static str,
+        path: Option<String>,
+        line: Option<usize>,
+        column: Option<usize>,
+        message: String,
+    },
+
+    /// A template couldn't be rendered. The causes explain the message,
+    /// starting with the most general one.
+    ///
+    Render {
+        template: String,
+        message: String,
+        causes: Vec<String>,
+    },
+
+    /// Values bound to variables don't match their schemas. Every violation
+    /// names the source of the value, and where in the value it occurred.
+    ///
+    Schema {
+        schemas: Vec<String>,
+        violations: Vec<String>,
+    },
+}
+
+impl Error {
+    /// Creates an error for data that a plugin couldn't parse.
+    ///
+    pub fn parse<M>(plugin: &'static str, message: M) -> Error
+    where
+        M: fmt::Display,
+    {
+        Error::Parse {
+            plugin,
+            path: None,
+            line: None,
+            column: None,
+            message: message.to_string(),
+        }
+    }
+
+    /// Sets the line and column that a parse error occurred at.
+    ///
+    pub fn at(self, line: usize, column: usize) -> Error {
+        match self {
+            Error::Parse {
+                plugin,
+                path,
+                message,
+                ..
+            } => Error::Parse {
+                plugin,
+                path,
+                line: Some(line),
+                column: Some(column),
+                message,
+            },
+            e => e,
+        }
+    }
+
+    /// Sets the path of the file that caused an I/O or parse error, if it
+    /// isn't already known.
+    ///
+    pub fn with_path<P>(self, path: P) -> Error
+    where
+        P: Into<String>,
+    {
+        match self {
+            Error::Io { path: None, error } => Error::Io {
+                path: Some(path.into()),
+                error,
+            },
+            Error::Parse {
+                plugin,
+                path: None,
+                line,
+                column,
+                message,
+            } => Error::Parse {
+                plugin,
+                path: Some(path.into()),
+                line,
+                column,
+                message,
+            },
+            e => e,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Usage(ref message) | Error::Mapping(ref message) => f.write_str(message),
+            Error::Io {
+                path: Some(ref path),
+                ref error,
+            } => write!(f, "{}: {}", path, error),
+            Error::Io {
+                path: None,
+                ref error,
+            } => write!(f, "{}", error),
+            Error::Parse {
+                plugin,
+                ref path,
+                line,
+                column,
+                ref message,
+            } => {
+                write!(f, "failed to parse")?;
+
+                if let Some(ref path) = *path {
+                    write!(f, " ({}", path)?;
+
+                    if let Some(line) = line {
+                        write!(f, ":{}", line)?;
+                    }
+
+                    if let Some(column) = column {
+                        write!(f, ":{}", column)?;
+                    }
+
+                    write!(f, ")")?;
+                }
+
+                write!(f, " with {} plugin: {}", plugin, message)
+            }
+            Error::Render {
+                ref template,
+                ref message,
+                ref causes,
+            } => {
+                write!(f, "failed to render template ({}): {}", template, message)?;
+
+                for cause in causes {
+                    write!(f, ": {}", cause)?;
+                }
+
+                Ok(())
+            }
+            Error::Schema {
+                ref schemas,
+                ref violations,
+            } => write!(
+                f,
+                "data doesn't match schema ({}): {}",
+                schemas.join(", "),
+                violations.join("; ")
+            ),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io { path: None, error }
+    }
+}
+
+/// Converts an error reading a path matched by a glob pattern.
+///
+#[cfg(feature = "glob")]
+impl From<GlobError> for Error {
+    fn from(e: GlobError) -> Error {
+        Error::from(io::Error::new(e.error().kind(), e.error().to_string()))
+            .with_path(e.path().to_string_lossy())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+
+    #[test]
+    fn test_parse_error_location() {
+        let e = Error::parse("json", "expected value")
+            .at(3, 7)
+            .with_path("data.json");
+
+        assert_eq!(
+            e.to_string(),
+            "failed to parse (data.json:3:7) with json plugin: expected value"
+        );
+    }
+
+    #[test]
+    fn test_path_is_not_replaced() {
+        let e = Error::parse("yaml", "invalid")
+            .with_path("inner.yml")
+            .with_path("outer.yml");
+
+        assert_eq!(
+            e.to_string(),
+            "failed to parse (inner.yml) with yaml plugin: invalid"
+        );
+    }
+
+    #[test]
+    fn test_render_error_causes() {
+        let e = Error::Render {
+            template: "page.html".into(),
+            message: "Failed to render".into(),
+            causes: vec!["Variable `title` not found".into()],
+        };
+
+        assert_eq!(
+            e.to_string(),
+            "failed to render template (page.html): Failed to render: Variable `title` not found"
+        );
+    }
+}
@@ -0,0 +1,338 @@
+use clap::{Arg, ArgMatches};
+use error::Error;
+use markdown::unescape;
+use std::{collections::HashMap, sync::OnceLock};
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{Color, Theme, ThemeSet},
+    html::{
+        append_highlighted_html_for_styled_line, css_for_theme_with_class_style, ClassStyle,
+        ClassedHTMLGenerator, IncludeBackground,
+    },
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+use tera::{GlobalFn, Value};
+
+/// The theme used if none is supplied.
+///
+pub const DEFAULT_THEME: &str = "InspiredGitHub";
+
+/// Classes are prefixed, so they don't collide with the classes of the page.
+///
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+
+/// The syntaxes bundled with syntect, which are only loaded once.
+///
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+
+    SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+/// The themes bundled with syntect, which are only loaded once.
+///
+fn theme_set() -> &'static ThemeSet {
+    static THEME_SET: OnceLock<ThemeSet> = OnceLock::new();
+
+    THEME_SET.get_or_init(ThemeSet::load_defaults)
+}
+
+/// The arguments that enable highlighting, and choose the theme. Each plugin
+/// supplies its own argument names.
+///
+pub fn args(style_arg: &'static str, theme_arg: &'static str) -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name(style_arg)
+            .long(style_arg)
+            .help(
+                "Highlight fenced code blocks with inline styles, or with CSS classes \
+                 (see the `highlight_css` function)",
+            )
+            .takes_value(true)
+            .possible_values(&["inline", "classes"]),
+        Arg::with_name(theme_arg)
+            .long(theme_arg)
+            .help("Theme used to highlight code blocks (e.g. InspiredGitHub, base16-ocean.dark)")
+            .takes_value(true)
+            .default_value(DEFAULT_THEME),
+    ]
+}
+
+/// Whether highlighted code is styled with inline styles or CSS classes.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HighlightStyle {
+    Inline,
+    Classes,
+}
+
+/// Highlights fenced code blocks in rendered Markdown.
+///
+#[derive(Debug, Clone)]
+pub struct Highlighter {
+    style: HighlightStyle,
+    theme: String,
+}
+
+impl Highlighter {
+    /// Creates a highlighter that uses a theme bundled with syntect.
+    ///
+    #[inline]
+    pub fn new<T>(style: HighlightStyle, theme: T) -> Highlighter
+    where
+        T: Into<String>,
+    {
+        Highlighter {
+            style,
+            theme: theme.into(),
+        }
+    }
+
+    /// Creates a highlighter from the command line arguments, if
+    /// highlighting is enabled.
+    ///
+    pub fn from_args<'a>(
+        args: &'a ArgMatches<'a>,
+        style_arg: &str,
+        theme_arg: &str,
+    ) -> Option<Highlighter> {
+        let style = match args.value_of(style_arg) {
+            Some("inline") => HighlightStyle::Inline,
+            Some("classes") => HighlightStyle::Classes,
+            _ => return None,
+        };
+
+        Some(Highlighter::new(
+            style,
+            args.value_of(theme_arg).unwrap_or(DEFAULT_THEME),
+        ))
+    }
+
+    /// Looks up the theme, listing the bundled themes if it doesn't exist.
+    ///
+    fn theme(&self) -> Result<&'static Theme, Error> {
+        let themes = &theme_set().themes;
+
+        themes.get(&self.theme).ok_or_else(|| {
+            let names: Vec<&str> = themes.keys().map(String::as_str).collect();
+
+            Error::Usage(format!(
+                "unknown highlighting theme ({}), expected one of: {}",
+                self.theme,
+                names.join(", ")
+            ))
+        })
+    }
+
+    /// Returns the stylesheet for code highlighted with CSS classes.
+    ///
+    pub fn css(&self) -> Result<String, Error> {
+        css_for_theme_with_class_style(self.theme()?, CLASS_STYLE)
+            .map_err(|e| Error::Usage(format!("failed to build stylesheet: {}", e)))
+    }
+
+    /// Returns a Tera function that outputs the stylesheet for the theme,
+    /// or for the theme passed as the `theme` argument.
+    ///
+    pub fn css_function(&self) -> GlobalFn {
+        let highlighter = self.clone();
+
+        Box::new(move |args: HashMap<String, Value>| {
+            let highlighter = match args.get("theme") {
+                Some(Value::String(theme)) => Highlighter::new(highlighter.style, theme.clone()),
+                Some(_) => return Err("`highlight_css` requires `theme` to be a string".into()),
+                None => highlighter.clone(),
+            };
+
+            highlighter
+                .css()
+                .map(Value::String)
+                .map_err(|e| e.to_string().into())
+        })
+    }
+
+    /// Highlights a block of code. Code in a language without a bundled
+    /// syntax is styled as plain text.
+    ///
+    fn highlight(&self, lang: &str, code: &str) -> Result<String, Error> {
+        let syntaxes = syntax_set();
+        let syntax = syntaxes
+            .find_syntax_by_token(lang)
+            .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+        let error = |e| Error::parse("highlight", e);
+
+        match self.style {
+            HighlightStyle::Inline => {
+                let theme = self.theme()?;
+                let bg = theme.settings.background.unwrap_or(Color::WHITE);
+                let mut lines = HighlightLines::new(syntax, theme);
+                let mut html = format!(
+                    "<pre style=\"background-color:#{:02x}{:02x}{:02x};\">",
+                    bg.r, bg.g, bg.b
+                );
+
+                html.push_str(&format!("<code class=\"language-{}\">", lang));
+
+                for line in LinesWithEndings::from(code) {
+                    let regions = lines.highlight_line(line, syntaxes).map_err(error)?;
+
+                    append_highlighted_html_for_styled_line(
+                        &regions[..],
+                        IncludeBackground::IfDifferent(bg),
+                        &mut html,
+                    )
+                    .map_err(error)?;
+                }
+
+                html.push_str("</code></pre>");
+
+                Ok(html)
+            }
+            HighlightStyle::Classes => {
+                let mut generator =
+                    ClassedHTMLGenerator::new_with_class_style(syntax, syntaxes, CLASS_STYLE);
+
+                for line in LinesWithEndings::from(code) {
+                    generator
+                        .parse_html_for_line_which_includes_newline(line)
+                        .map_err(error)?;
+                }
+
+                Ok(format!(
+                    "<pre class=\"hl-code\"><code class=\"language-{}\">{}</code></pre>",
+                    lang,
+                    generator.finalize()
+                ))
+            }
+        }
+    }
+
+    /// Highlights every fenced code block with a language in rendered html.
+    /// Both `<pre><code class="language-x">` and `<pre lang="x"><code>`
+    /// blocks are recognized. Any other `<pre>` block is kept as is.
+    ///
+    pub fn highlight_html(&self, html: &str) -> Result<String, Error> {
+        const CLOSE: &str = "</pre>";
+
+        let mut out = String::with_capacity(html.len());
+        let mut rest = html;
+
+        while let Some(start) = find_pre(rest) {
+            let end = match rest[start..].find(CLOSE) {
+                Some(end) => start + end + CLOSE.len(),
+                None => break,
+            };
+            let block = &rest[start..end];
+
+            out.push_str(&rest[..start]);
+
+            match code_block(block) {
+                Some((lang, code)) => out.push_str(&self.highlight(lang, &unescape(code))?),
+                None => out.push_str(block),
+            }
+
+            rest = &rest[end..];
+        }
+
+        out.push_str(rest);
+
+        Ok(out)
+    }
+}
+
+/// Finds the start of the next `<pre>` tag, skipping tags that only start
+/// with `pre` (e.g. `<preview>`).
+///
+fn find_pre(html: &str) -> Option<usize> {
+    let mut offset = 0;
+
+    while let Some(start) = html[offset..].find("<pre") {
+        let start = offset + start;
+
+        match html.as_bytes().get(start + 4) {
+            Some(b'>') | Some(b' ') | Some(b'\t') | Some(b'\n') => return Some(start),
+            _ => offset = start + 4,
+        }
+    }
+
+    None
+}
+
+/// Splits a `<pre>...</pre>` block into the language and the escaped code of
+/// the `<code>` element it wraps, if it wraps one with a language.
+///
+fn code_block(block: &str) -> Option<(&str, &str)> {
+    const END: &str = "</code></pre>";
+
+    let open_end = block
+        .find("<code")
+        .and_then(|code| block[code..].find('>').map(|end| code + end + 1))?;
+
+    if !block.ends_with(END) {
+        return None;
+    }
+
+    language(&block[..open_end]).map(|lang| (lang, &block[open_end..block.len() - END.len()]))
+}
+
+/// Reads the language from the opening tags of a code block.
+///
+fn language(open: &str) -> Option<&str> {
+    ["class=\"language-", "lang=\""]
+        .iter()
+        .filter_map(|attr| open.find(attr).map(|i| &open[i + attr.len()..]))
+        .next()
+        .and_then(|lang| lang.find('"').map(|end| &lang[..end]))
+        .filter(|lang| !lang.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HighlightStyle, Highlighter};
+
+    #[test]
+    fn test_highlight_with_classes() {
+        let highlighter = Highlighter::new(HighlightStyle::Classes, super::DEFAULT_THEME);
+        let html = highlighter
+            .highlight_html(
+                "<p>a</p>\n<pre><code class=\"language-rust\">let x = &amp;1;\n</code></pre>\n",
+            )
+            .unwrap();
+
+        assert!(html.starts_with("<p>a</p>\n<pre class=\"hl-code\"><code class=\"language-rust\">"));
+        assert!(html.contains("<span class=\"hl-storage hl-type hl-rust\">let</span>"));
+        assert!(html.ends_with("</code></pre>\n"));
+    }
+
+    #[test]
+    fn test_blocks_without_language_are_kept() {
+        let highlighter = Highlighter::new(HighlightStyle::Inline, super::DEFAULT_THEME);
+        let html = "<pre><code>plain &lt;text&gt;\n</code></pre>\n";
+
+        assert_eq!(highlighter.highlight_html(html).unwrap(), html);
+    }
+
+    #[test]
+    fn test_raw_pre_blocks_are_kept() {
+        let highlighter = Highlighter::new(HighlightStyle::Classes, super::DEFAULT_THEME);
+        let html = highlighter
+            .highlight_html(
+                "<pre>RAW ASCII ART</pre>\n<p>KEEP ME</p>\n<preview>x</preview>\n<pre><code \
+                 class=\"language-rust\">let x = 1;\n</code></pre>\n",
+            )
+            .unwrap();
+
+        assert!(html.starts_with(
+            "<pre>RAW ASCII ART</pre>\n<p>KEEP ME</p>\n<preview>x</preview>\n<pre class=\"hl-code\">"
+        ));
+        assert!(html.contains("<span class=\"hl-storage hl-type hl-rust\">let</span>"));
+    }
+
+    #[test]
+    fn test_unknown_theme() {
+        let highlighter = Highlighter::new(HighlightStyle::Inline, "missing");
+
+        assert!(highlighter.css().is_err());
+    }
+}
@@ -1,17 +1,56 @@
 extern crate clap;
+#[cfg(feature = "glob")]
+extern crate glob;
+#[cfg(feature = "schema")]
+extern crate jsonschema;
 extern crate serde;
+#[cfg(feature = "schema")]
+extern crate serde_json;
 #[cfg(feature = "markdown")]
 extern crate serde_yaml;
+#[cfg(feature = "highlight")]
+extern crate syntect;
 #[cfg(feature = "markdown")]
 #[macro_use]
 extern crate serde_derive;
+#[cfg_attr(feature = "markdown", macro_use)]
+extern crate tera;
 
 use clap::{Arg, ArgMatches};
 use serde::Serialize;
-use std::{cmp, borrow::Cow, io::{self, Read}};
-
+use std::{
+    borrow::Cow,
+    cmp,
+    io::{self, Read},
+};
+use tera::Tera;
+
+mod error;
+#[cfg(feature = "highlight")]
+pub mod highlight;
+#[cfg(not(feature = "highlight"))]
+pub mod highlight {
+    use clap::Arg;
+
+    /// Syntax highlighting isn't available without the highlight feature, so
+    /// there are no arguments to enable it.
+    ///
+    pub fn args(_: &'static str, _: &'static str) -> Vec<Arg<'static, 'static>> {
+        Vec::new()
+    }
+}
+mod mapping;
 #[cfg(feature = "markdown")]
 pub mod markdown;
+#[cfg(feature = "schema")]
+pub mod schema;
+mod select;
+mod variables;
+
+pub use error::Error;
+pub use mapping::VarMapping;
+pub use select::Selector;
+pub use variables::{Merge, Variables};
 
 /// Macro to define very simple lists of clap arguments.
 ///
@@ -26,51 +65,6 @@ macro_rules! args {
     };
 }
 
-/// Defines a mapping between a template variable, and the file to
-/// populate it.
-///
-#[derive(Debug)]
-pub struct VarMapping<'a> {
-    var_name: &'a str,
-    arg_value: &'a str,
-}
-
-impl<'a> VarMapping<'a> {
-    /// Unpacks a mapped variable string `<var_name>:<arg_value>`.
-    ///
-    pub fn from_str(s: &'a str) -> Result<VarMapping<'a>, String> {
-        let mut splits = s.splitn(2, ":");
-        let var_name = if let Some(var_name) = splits.next() {
-            var_name
-        } else {
-            return Err(format!("Expected a variable name to bind to in ({})", s));
-        };
-        let arg_value = if let Some(arg_value) = splits.next() {
-            arg_value
-        } else {
-            return Err(format!(
-                "Expected a value to bind to ({}) in ({})",
-                var_name, s
-            ));
-        };
-
-        Ok(VarMapping {
-            var_name,
-            arg_value,
-        })
-    }
-
-    #[inline]
-    pub fn arg_value(&self) -> &'a str {
-        self.arg_value
-    }
-
-    #[inline]
-    pub fn var_name(&self) -> &'a str {
-        self.var_name
-    }
-}
-
 /// How to interpret the value of an argument.
 ///
 pub enum Interpretation {
@@ -100,13 +94,13 @@ where
 {
     /// Consumes the source, and reads the entire value into a string.
     ///
-    pub fn consume(self) -> Result<Cow<'a, str>, String> {
+    pub fn consume(self) -> Result<Cow<'a, str>, Error> {
         match self {
             Source::Raw(raw, _) => Ok(Cow::Borrowed(raw)),
             Source::File(mut reader) => {
                 let mut buf = String::new();
 
-                reader.read_to_string(&mut buf).map_err(|e| e.to_string())?;
+                reader.read_to_string(&mut buf)?;
 
                 Ok(Cow::Owned(buf))
             }
@@ -119,23 +113,23 @@ where
     R: Read,
 {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
-        match self {
+        match *self {
             // For raw strings, the raw bytes can just be copied
             // to the buffer. An index value is tracked to only copy
             // unseen bytes.
-            &mut Source::Raw(raw, ref mut index) => {
+            Source::Raw(raw, ref mut index) => {
                 let current = *index;
                 let slice = &raw.as_bytes()[current..];
                 let copy_num = cmp::min(buf.len(), slice.len());
 
-                &buf[..copy_num].copy_from_slice(&slice[..copy_num]);
+                buf[..copy_num].copy_from_slice(&slice[..copy_num]);
                 *index = current + copy_num;
 
                 Ok(copy_num)
             }
             // For wrappers around file handlers, the data can just
             // be read directly from the buffer.
-            &mut Source::File(ref mut reader) => reader.read(buf),
+            Source::File(ref mut reader) => reader.read(buf),
         }
     }
 }
@@ -160,6 +154,27 @@ pub trait CompileVariablePlugin {
     ///
     const ARG_INTERPRETATION: Interpretation;
 
+    /// Whether a selector can follow a path (e.g. `company.json#/products`)
+    /// to bind only part of the data read from it. See `Selector`.
+    ///
+    const SELECTORS: bool = false;
+
+    /// The variable bound by a mapping without a variable name (e.g.
+    /// `--env-prefix CRYOGEN_`). Mappings need a variable name by default.
+    ///
+    const DEFAULT_VAR_NAME: Option<&'static str> = None;
+
+    /// The part of the value that a schema validates, as a JSON Pointer (e.g.
+    /// `/metadata` to validate only the front matter of a document). The whole
+    /// value is validated by default.
+    ///
+    const SCHEMA_POINTER: &'static str = "";
+
+    /// The argument without which the part of the value that a schema
+    /// validates isn't read (e.g. `markdown-yaml-metadata`), if there is one.
+    ///
+    const SCHEMA_REQUIRES: Option<&'static str> = None;
+
     /// The help string to display.
     ///
     const HELP: &'static str;
@@ -174,7 +189,14 @@ pub trait CompileVariablePlugin {
 
     /// Reads the source data, and parses it into a value that can be rendered.
     ///
-    fn read<'a, R>(&self, src: Source<'a, R>) -> Result<Self::RenderValue, String>
+    fn read<'a, R>(&self, src: Source<'a, R>) -> Result<Self::RenderValue, Error>
     where
         R: Read;
+
+    /// Registers filters, testers, and global functions that templates can
+    /// use, whether or not the plugin assigns any variables. Does nothing by
+    /// default.
+    ///
+    #[inline]
+    fn extend_tera(&self, _tera: &mut Tera) {}
 }
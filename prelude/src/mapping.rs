@@ -0,0 +1,319 @@
+use error::Error;
+
+/// Reads a dotted variable name from the start of a mapping, up to the
+/// first unquoted, unescaped `terminator`. Returns the segments of the name,
+/// and the byte offset of the terminator.
+///
+fn read_var_path(s: &str, terminator: char) -> Result<(Vec<String>, usize), Error> {
+    let mut segments = Vec::new();
+    let mut segment = String::new();
+    let mut chars = s.char_indices();
+
+    let empty_segment = || {
+        Error::Mapping(format!(
+            "Expected a variable name without empty segments in ({})",
+            s
+        ))
+    };
+    let unfinished = |what: &str| {
+        Error::Mapping(format!(
+            "Expected the variable name to end with a complete {} in ({})",
+            what, s
+        ))
+    };
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c == terminator => {
+                if segments.is_empty() && segment.is_empty() && i == 0 {
+                    return Err(Error::Mapping(format!(
+                        "Expected a variable name to bind to in ({})",
+                        s
+                    )));
+                }
+
+                if segment.is_empty() {
+                    return Err(empty_segment());
+                }
+
+                segments.push(segment);
+
+                return Ok((segments, i));
+            }
+            '.' if segment.is_empty() => return Err(empty_segment()),
+            '.' => segments.push(::std::mem::take(&mut segment)),
+            '\\' => match chars.next() {
+                Some((_, c)) => segment.push(c),
+                None => return Err(unfinished("escape")),
+            },
+            '"' => loop {
+                match chars.next() {
+                    Some((_, '"')) => break,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, c)) => segment.push(c),
+                        None => return Err(unfinished("escape")),
+                    },
+                    Some((_, c)) => segment.push(c),
+                    None => return Err(unfinished("quote")),
+                }
+            },
+            '\'' => loop {
+                match chars.next() {
+                    Some((_, '\'')) => break,
+                    Some((_, c)) => segment.push(c),
+                    None => return Err(unfinished("quote")),
+                }
+            },
+            c => segment.push(c),
+        }
+    }
+
+    segments.push(segment);
+
+    Err(Error::Mapping(format!(
+        "Expected a value to bind to ({}) in ({})",
+        segments.join("."),
+        s
+    )))
+}
+
+/// Defines a mapping between a template variable, and the file to
+/// populate it. Mappings are written as `<var_name>:<arg_value>` (e.g.
+/// `--yaml site.nav:nav.yml`), or as `<var_name>=<plugin>:<arg_value>` with
+/// the bind argument (e.g. `--bind site.nav=yaml:nav.yml`).
+///
+/// The variable name is made of segments separated by `.`, which bind
+/// nested variables. Within a segment:
+///
+/// * a backslash escapes the next character (e.g. `a\:b` or `a\.b`),
+/// * double quotes wrap text that can contain `:`, `=`, and `.`, with
+///   backslash escapes (e.g. `"a:b"`),
+/// * single quotes wrap text that is taken as is (e.g. `'C:\dir'`).
+///
+/// Segments can't be empty. The name ends at the first unquoted, unescaped
+/// `:` (or `=` for the bind argument), and everything after it is the
+/// value, taken as is. The value can contain colons (e.g. URLs, or
+/// `C:\data.json`), and can be empty. A mapping like `C:\data.json` is
+/// rejected, since it's a Windows path without a variable name.
+///
+#[derive(Debug)]
+pub struct VarMapping<'a> {
+    var_path: Vec<String>,
+    arg_value: &'a str,
+}
+
+impl<'a> VarMapping<'a> {
+    /// Unpacks a mapped variable string `<var_name>:<arg_value>`. The
+    /// variable name can be dotted (e.g. `site.nav`) to bind a nested
+    /// variable.
+    ///
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &'a str) -> Result<VarMapping<'a>, Error> {
+        let (var_path, end) = read_var_path(s, ':')?;
+        let arg_value = &s[end + 1..];
+
+        // A single letter followed by a backslash is a Windows drive.
+        //
+        if end == 1 && s.as_bytes()[0].is_ascii_alphabetic() && arg_value.starts_with('\\') {
+            return Err(Error::Mapping(format!(
+                "Expected a variable name to bind to in ({}), which looks like a Windows path \
+                 (e.g. data:{})",
+                s, s
+            )));
+        }
+
+        Ok(VarMapping {
+            var_path,
+            arg_value,
+        })
+    }
+
+    /// Unpacks a mapped variable string like `from_str`, but binds a value
+    /// without a `:` to a default variable instead.
+    ///
+    pub fn with_default_name(s: &'a str, var_name: &str) -> Result<VarMapping<'a>, Error> {
+        if s.contains(':') {
+            VarMapping::from_str(s)
+        } else {
+            Ok(VarMapping {
+                var_path: vec![var_name.to_string()],
+                arg_value: s,
+            })
+        }
+    }
+
+    /// Unpacks a bind argument `<var_name>=<plugin>:<arg_value>`, returning
+    /// the name of the plugin along with the mapping.
+    ///
+    pub fn from_bind(s: &'a str) -> Result<(&'a str, VarMapping<'a>), Error> {
+        let (var_path, end) = read_var_path(s, '=')?;
+        let mut splits = s[end + 1..].splitn(2, ':');
+
+        match (splits.next(), splits.next()) {
+            (Some(plugin), Some(arg_value)) if !plugin.is_empty() => Ok((
+                plugin,
+                VarMapping {
+                    var_path,
+                    arg_value,
+                },
+            )),
+            _ => Err(Error::Mapping(format!(
+                "Expected a plugin and a value (e.g. name=yaml:data.yml) in ({})",
+                s
+            ))),
+        }
+    }
+
+    /// Escapes a segment of a variable name, so it's read back as a single
+    /// segment (e.g. `a.b` becomes `a\.b`), however many special characters
+    /// it contains.
+    ///
+    pub fn escape(segment: &str) -> String {
+        let mut escaped = String::with_capacity(segment.len());
+
+        for c in segment.chars() {
+            if let '\\' | '.' | ':' | '=' | '"' | '\'' = c {
+                escaped.push('\\');
+            }
+
+            escaped.push(c);
+        }
+
+        escaped
+    }
+
+    #[inline]
+    pub fn arg_value(&self) -> &'a str {
+        self.arg_value
+    }
+
+    /// Returns the segments of the variable name.
+    ///
+    #[inline]
+    pub fn var_path(&self) -> &[String] {
+        &self.var_path[..]
+    }
+
+    /// Returns the variable name, with its segments joined by `.`.
+    ///
+    #[inline]
+    pub fn var_name(&self) -> String {
+        self.var_path.join(".")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VarMapping;
+
+    fn parse(s: &str) -> (Vec<String>, &str) {
+        let mapping = VarMapping::from_str(s).unwrap();
+
+        (mapping.var_path().to_vec(), mapping.arg_value())
+    }
+
+    fn error(s: &str) -> String {
+        VarMapping::from_str(s).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_simple_mapping() {
+        assert_eq!(
+            parse("posts:posts.yml"),
+            (vec!["posts".into()], "posts.yml")
+        );
+        assert_eq!(
+            parse("site.nav:nav.yml"),
+            (vec!["site".into(), "nav".into()], "nav.yml")
+        );
+    }
+
+    #[test]
+    fn test_value_with_colons() {
+        assert_eq!(
+            parse("url:https://example.com:8080/"),
+            (vec!["url".into()], "https://example.com:8080/")
+        );
+        assert_eq!(
+            parse("data:C:\\data\\site.json"),
+            (vec!["data".into()], "C:\\data\\site.json")
+        );
+    }
+
+    #[test]
+    fn test_empty_value() {
+        assert_eq!(parse("title:"), (vec!["title".into()], ""));
+    }
+
+    #[test]
+    fn test_escaped_and_quoted_names() {
+        assert_eq!(parse("a\\:b:x"), (vec!["a:b".into()], "x"));
+        assert_eq!(parse("a\\.b.c:x"), (vec!["a.b".into(), "c".into()], "x"));
+        assert_eq!(
+            parse("\"a:b\\\"c\".d:x"),
+            (vec!["a:b\"c".into(), "d".into()], "x")
+        );
+        assert_eq!(parse("'C:\\dir':x"), (vec!["C:\\dir".into()], "x"));
+    }
+
+    #[test]
+    fn test_invalid_names() {
+        assert_eq!(error(":x"), "Expected a variable name to bind to in (:x)");
+        assert_eq!(
+            error("a..b:x"),
+            "Expected a variable name without empty segments in (a..b:x)"
+        );
+        assert_eq!(
+            error("a.:x"),
+            "Expected a variable name without empty segments in (a.:x)"
+        );
+        assert_eq!(
+            error("\"a:x"),
+            "Expected the variable name to end with a complete quote in (\"a:x)"
+        );
+        assert_eq!(
+            error("title"),
+            "Expected a value to bind to (title) in (title)"
+        );
+    }
+
+    #[test]
+    fn test_windows_path_without_name() {
+        assert!(error("C:\\data.json").contains("looks like a Windows path"));
+        assert_eq!(parse("c:/data.json"), (vec!["c".into()], "/data.json"));
+    }
+
+    #[test]
+    fn test_default_name() {
+        let mapping = VarMapping::with_default_name("CRYOGEN_", "env").unwrap();
+
+        assert_eq!(mapping.var_name(), "env");
+        assert_eq!(mapping.arg_value(), "CRYOGEN_");
+
+        let mapping = VarMapping::with_default_name("ci:CI_", "env").unwrap();
+
+        assert_eq!(mapping.var_name(), "ci");
+        assert_eq!(mapping.arg_value(), "CI_");
+    }
+
+    #[test]
+    fn test_escape() {
+        for name in &["title", "a.b", "a:b", "x=\"y\"", "'C:\\dir'"] {
+            let mapping = format!("{}:x", VarMapping::escape(name));
+
+            assert_eq!(parse(&mapping), (vec![name.to_string()], "x"));
+        }
+    }
+
+    #[test]
+    fn test_bind() {
+        let (plugin, mapping) = VarMapping::from_bind("site.nav=yaml:C:\\nav.yml").unwrap();
+
+        assert_eq!(plugin, "yaml");
+        assert_eq!(mapping.var_name(), "site.nav");
+        assert_eq!(mapping.arg_value(), "C:\\nav.yml");
+        assert!(VarMapping::from_bind("site=nav.yml").is_err());
+        assert!(VarMapping::from_bind("site=:nav.yml").is_err());
+        assert!(VarMapping::from_bind("=yaml:nav.yml").is_err());
+    }
+}
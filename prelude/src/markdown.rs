@@ -1,15 +1,59 @@
+use error::Error;
 use serde_yaml;
+use std::collections::{HashMap, HashSet};
+use tera::{self, Value};
 
 /// The Markdown metadata holder is a YAML object.
 ///
 pub type MarkdownMetadata = serde_yaml::Value;
 
+/// A heading in a table of contents, with the headings nested under it.
+///
+#[derive(Serialize, Debug, PartialEq)]
+pub struct TocEntry {
+    level: u8,
+    text: String,
+    anchor: String,
+    children: Vec<TocEntry>,
+}
+
+impl TocEntry {
+    /// Returns the level of the heading (1 to 6).
+    ///
+    #[inline]
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    /// Returns the text of the heading, without any markup.
+    ///
+    #[inline]
+    pub fn text(&self) -> &str {
+        &self.text[..]
+    }
+
+    /// Returns the id of the heading, which can be linked to with `#anchor`.
+    ///
+    #[inline]
+    pub fn anchor(&self) -> &str {
+        &self.anchor[..]
+    }
+
+    /// Returns the headings nested under this one.
+    ///
+    #[inline]
+    pub fn children(&self) -> &[TocEntry] {
+        &self.children[..]
+    }
+}
+
 /// Value written to Tera context.
 ///
 #[derive(Serialize)]
 pub struct RenderedMarkdown {
     metadata: Option<MarkdownMetadata>,
     html: String,
+    toc: Vec<TocEntry>,
 }
 
 impl RenderedMarkdown {
@@ -17,7 +61,20 @@ impl RenderedMarkdown {
     ///
     #[inline]
     pub fn new(metadata: Option<MarkdownMetadata>, html: String) -> RenderedMarkdown {
-        RenderedMarkdown { metadata, html }
+        RenderedMarkdown {
+            metadata,
+            html,
+            toc: Vec::new(),
+        }
+    }
+
+    /// Adds an id to every heading in the rendered html, and builds a table
+    /// of contents from them.
+    ///
+    pub fn with_heading_ids(self) -> RenderedMarkdown {
+        let (html, toc) = heading_ids(&self.html);
+
+        RenderedMarkdown { html, toc, ..self }
     }
 
     /// Returns YAML metadata.
@@ -37,27 +94,155 @@ impl RenderedMarkdown {
     pub fn html(&self) -> &str {
         &self.html[..]
     }
+
+    /// Returns the table of contents, which is empty unless heading ids were
+    /// added.
+    ///
+    #[inline]
+    pub fn toc(&self) -> &[TocEntry] {
+        &self.toc[..]
+    }
 }
 
-const DIVIDER: &[u8] = &[b'-', b'-', b'-'];
-const NEWLINE: &[u8] = &[b'\n'];
-const NEWLINE_ALT: &[u8] = &[b'\r', b'\n'];
+/// Converts the text of a heading into a slug that can be used as an id.
+/// Letters and numbers are lowercased, spaces and dashes become dashes, and
+/// everything else is removed.
+///
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+
+    for c in text.trim().chars() {
+        if c.is_alphanumeric() || c == '_' {
+            slug.extend(c.to_lowercase());
+        } else if (c.is_whitespace() || c == '-') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    slug.trim_matches('-').to_string()
+}
+
+/// Decodes the entities that Markdown renderers escape.
+///
+pub(crate) fn unescape(html: &str) -> String {
+    html.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Removes tags from a fragment of html, and decodes its entities.
+///
+fn html_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => (),
+        }
+    }
 
-/// Tries to read a YAML block from the beginning of an input buffer.
+    unescape(&text)
+}
+
+/// Adds an entry to a table of contents, nesting it under the last entry if
+/// that entry is a higher level heading.
 ///
-pub fn read_header(mut buf: &[u8]) -> Result<(Option<MarkdownMetadata>, usize), String> {
+fn insert_entry(toc: &mut Vec<TocEntry>, entry: TocEntry) {
+    match toc.last_mut() {
+        Some(ref mut last) if last.level < entry.level => insert_entry(&mut last.children, entry),
+        _ => toc.push(entry),
+    }
+}
+
+/// Adds a unique id to every heading (`<h1>` to `<h6>`) in rendered html,
+/// and returns the html with a table of contents. Repeated slugs get a
+/// numbered suffix (e.g. `intro`, `intro-1`), in the order they appear.
+///
+pub fn heading_ids(html: &str) -> (String, Vec<TocEntry>) {
+    let mut out = String::with_capacity(html.len());
+    let mut toc = Vec::new();
+    let mut used = HashSet::new();
+    let mut rest = html;
+
+    while let Some(start) = rest.find("<h") {
+        let bytes = rest.as_bytes();
+        let level = match bytes.get(start + 2) {
+            Some(&b) if (b'1'..=b'6').contains(&b) && bytes.get(start + 3) == Some(&b'>') => {
+                b - b'0'
+            }
+            _ => {
+                out.push_str(&rest[..start + 2]);
+                rest = &rest[start + 2..];
+                continue;
+            }
+        };
+        let close = format!("</h{}>", level);
+        let inner_start = start + 4;
+        let inner_end = match rest[inner_start..].find(&close) {
+            Some(end) => inner_start + end,
+            None => break,
+        };
+        let inner = &rest[inner_start..inner_end];
+        let text = html_text(inner);
+        let slug = match slugify(&text) {
+            ref slug if slug.is_empty() => "section".to_string(),
+            slug => slug,
+        };
+        let mut anchor = slug.clone();
+        let mut n = 0;
+
+        while used.contains(&anchor) {
+            n += 1;
+            anchor = format!("{}-{}", slug, n);
+        }
+
+        used.insert(anchor.clone());
+        out.push_str(&rest[..start]);
+        out.push_str(&format!("<h{} id=\"{}\">{}{}", level, anchor, inner, close));
+        insert_entry(
+            &mut toc,
+            TocEntry {
+                level,
+                text,
+                anchor,
+                children: Vec::new(),
+            },
+        );
+        rest = &rest[inner_end + close.len()..];
+    }
+
+    out.push_str(rest);
+
+    (out, toc)
+}
+
+const DIVIDER: &[u8] = b"---";
+const NEWLINE: &[u8] = b"\n";
+const NEWLINE_ALT: &[u8] = b"\r\n";
+
+/// Tries to read a block delimited by lines containing only `divider` from
+/// the beginning of an input buffer. Returns the contents of the block, and
+/// the total number of bytes read (including the dividers).
+///
+pub fn read_block<'a>(mut buf: &'a [u8], divider: &[u8]) -> Option<(&'a [u8], usize)> {
     let mut total_read = 0;
-    // Check to see if the buffer starts with the YAML block divider.
+    // Check to see if the buffer starts with the block divider.
     //
-    if !buf.starts_with(DIVIDER) {
-        return Ok((None, 0));
+    if !buf.starts_with(divider) {
+        return None;
     }
 
-    total_read += DIVIDER.len();
+    total_read += divider.len();
 
-    buf = &buf[DIVIDER.len()..];
+    buf = &buf[divider.len()..];
 
-    // Check to see if the DIVIDER is followed by a new line.
+    // Check to see if the divider is followed by a new line.
     //
     if buf.starts_with(NEWLINE_ALT) {
         total_read += NEWLINE_ALT.len();
@@ -68,82 +253,182 @@ pub fn read_header(mut buf: &[u8]) -> Result<(Option<MarkdownMetadata>, usize),
 
         buf = &buf[NEWLINE.len()..];
     } else {
-        return Ok((None, 0));
+        return None;
     }
 
-    let mut index = 0;
-    let mut yaml_end = None;
-    for part in buf.windows(DIVIDER.len()) {
-        if part == DIVIDER {
-            let after_index = index + DIVIDER.len();
+    for (index, part) in buf.windows(divider.len()).enumerate() {
+        if part == divider {
+            let after_index = index + divider.len();
 
-            // The YAML block could end at the EOF.
+            // The block could end at the EOF.
             //
-            // Usually the YAML block will end before the
+            // Usually the block will end before the
             // end of the file, and a newline will signify
             // the start of the Markdown code.
             //
-            if after_index > buf.len() {
-                total_read += after_index;
-
-                yaml_end = Some(index);
-
-                break;
+            if after_index == buf.len() {
+                return Some((&buf[..index], total_read + after_index));
             }
 
             if buf[after_index..].starts_with(NEWLINE) {
-                total_read += after_index + NEWLINE.len();
-
-                yaml_end = Some(index);
-
-                break;
+                return Some((&buf[..index], total_read + after_index + NEWLINE.len()));
             }
             if buf[after_index..].starts_with(NEWLINE_ALT) {
-                total_read += after_index + NEWLINE_ALT.len();
-
-                yaml_end = Some(index);
-
-                break;
+                return Some((&buf[..index], total_read + after_index + NEWLINE_ALT.len()));
             }
         }
-
-        index += 1;
     }
 
-    match yaml_end {
-        Some(0) => Ok((None, total_read)),
-        Some(end) => serde_yaml::from_slice(&buf[..end])
+    None
+}
+
+/// Tries to read a YAML block from the beginning of an input buffer. Parse
+/// errors are attributed to `plugin`.
+///
+pub fn read_header(
+    plugin: &'static str,
+    buf: &[u8],
+) -> Result<(Option<MarkdownMetadata>, usize), Error> {
+    match read_block(buf, DIVIDER) {
+        Some(([], total_read)) => Ok((None, total_read)),
+        Some((block, total_read)) => serde_yaml::from_slice(block)
             .map(|meta| (Some(meta), total_read))
-            .map_err(|e| e.to_string()),
+            .map_err(|e| match e.location() {
+                // The block starts on the line after the opening divider.
+                //
+                Some(location) => {
+                    Error::parse(plugin, &e).at(location.line() + 1, location.column())
+                }
+                None => Error::parse(plugin, &e),
+            }),
         None => Ok((None, 0)),
     }
 }
 
+/// Removes the paragraph wrapping HTML rendered from a single paragraph of
+/// Markdown, so it can be used inline. Any other HTML is returned as is.
+///
+pub fn strip_paragraph(html: &str) -> &str {
+    let trimmed = html.trim_end();
+
+    if trimmed.starts_with("<p>") && trimmed.ends_with("</p>") {
+        let inner = &trimmed[3..trimmed.len() - 4];
+
+        if !inner.contains("<p>") && !inner.contains("</p>") {
+            return inner;
+        }
+    }
+
+    html
+}
+
+/// Renders the Markdown string passed to a Tera filter. If the filter is
+/// called with `inline=true`, the wrapping paragraph is removed.
+///
+pub fn render_filter<F>(
+    filter: &str,
+    value: Value,
+    args: &HashMap<String, Value>,
+    render: F,
+) -> tera::Result<Value>
+where
+    F: FnOnce(&str) -> String,
+{
+    let data = try_get_value!(filter, "value", String, value);
+    let inline = match args.get("inline") {
+        Some(inline) => try_get_value!(filter, "inline", bool, inline),
+        None => false,
+    };
+    let html = render(&data);
+
+    if inline {
+        Ok(Value::String(strip_paragraph(&html).to_string()))
+    } else {
+        Ok(Value::String(html))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
     fn test_empty_prelude_recognized() {
         let yaml_block = b"---\n---\n";
 
-        assert!(super::read_header(yaml_block).is_ok())
+        assert!(super::read_header("markdown", yaml_block).is_ok())
     }
 
     #[test]
     fn test_empty_string() {
-        assert!(super::read_header(b"").is_ok());
+        assert!(super::read_header("markdown", b"").is_ok());
     }
 
     #[test]
     fn test_incomplete_block() {
-        assert!(super::read_header(b"---").is_ok());
+        assert!(super::read_header("markdown", b"---").is_ok());
     }
 
     #[test]
-    fn test_valid_block() {
-        assert!(
-            super::read_header(b"---\ntitle: ABC\n---\n")
-                .map(|(res, _)| res.is_some())
-                .unwrap_or(false)
+    fn test_block_at_eof() {
+        assert!(super::read_header("markdown", b"---\ntitle: ABC\n---")
+            .map(|(res, _)| res.is_some())
+            .unwrap_or(false));
+    }
+
+    #[test]
+    fn test_custom_divider() {
+        assert_eq!(
+            super::read_block(b"+++\ntitle = 1\n+++\nbody", b"+++"),
+            Some((&b"title = 1\n"[..], 18))
+        );
+    }
+
+    #[test]
+    fn test_strip_single_paragraph() {
+        assert_eq!(
+            super::strip_paragraph("<p><em>hi</em></p>\n"),
+            "<em>hi</em>"
+        );
+    }
+
+    #[test]
+    fn test_keep_many_paragraphs() {
+        let html = "<p>one</p>\n<p>two</p>\n";
+
+        assert_eq!(super::strip_paragraph(html), html);
+        assert_eq!(super::strip_paragraph("<h1>hi</h1>\n"), "<h1>hi</h1>\n");
+    }
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(super::slugify(" Hello, World! "), "hello-world");
+        assert_eq!(
+            super::slugify("Step 2 -- Install `cryogen`"),
+            "step-2-install-cryogen"
+        );
+    }
+
+    #[test]
+    fn test_heading_ids() {
+        let (html, toc) = super::heading_ids(
+            "<h1>Intro</h1>\n<p>a</p>\n<h2>A &amp; <em>B</em></h2>\n<h3>C</h3>\n<h2>Intro</h2>\n",
+        );
+
+        assert_eq!(
+            html,
+            "<h1 id=\"intro\">Intro</h1>\n<p>a</p>\n<h2 id=\"a-b\">A &amp; <em>B</em></h2>\n\
+             <h3 id=\"c\">C</h3>\n<h2 id=\"intro-1\">Intro</h2>\n"
         );
+        assert_eq!(toc.len(), 1);
+        assert_eq!(toc[0].children().len(), 2);
+        assert_eq!(toc[0].children()[0].text(), "A & B");
+        assert_eq!(toc[0].children()[0].children()[0].anchor(), "c");
+        assert_eq!(toc[0].children()[1].anchor(), "intro-1");
+    }
+
+    #[test]
+    fn test_valid_block() {
+        assert!(super::read_header("markdown", b"---\ntitle: ABC\n---\n")
+            .map(|(res, _)| res.is_some())
+            .unwrap_or(false));
     }
 }
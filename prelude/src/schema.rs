@@ -0,0 +1,145 @@
+use error::Error;
+use jsonschema::Validator;
+use serde_json;
+use std::{fs::File, io};
+use tera::Value;
+
+/// A JSON Schema that the values bound to a variable are validated against.
+///
+pub struct Schema {
+    path: String,
+    validator: Validator,
+}
+
+impl Schema {
+    /// Compiles a schema. The path is only used to describe the schema in
+    /// errors.
+    ///
+    pub fn new<P>(path: P, schema: &Value) -> Result<Schema, Error>
+    where
+        P: Into<String>,
+    {
+        let path = path.into();
+        let validator = jsonschema::validator_for(schema)
+            .map_err(|e| Error::Usage(format!("invalid schema ({}): {}", path, e)))?;
+
+        Ok(Schema { path, validator })
+    }
+
+    /// Reads and compiles a schema from a JSON file.
+    ///
+    pub fn read(path: &str) -> Result<Schema, Error> {
+        let file = File::open(path).map_err(|e| Error::from(e).with_path(path))?;
+        let schema: Value = serde_json::from_reader(file).map_err(|e| {
+            if e.is_io() {
+                Error::from(io::Error::from(e)).with_path(path)
+            } else {
+                Error::parse("json", &e)
+                    .at(e.line(), e.column())
+                    .with_path(path)
+            }
+        })?;
+
+        Schema::new(path, &schema)
+    }
+
+    /// Returns the path of the schema.
+    ///
+    #[inline]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns every violation in values read from their sources (e.g. file
+    /// paths), along with its source and the JSON Pointer of the value it
+    /// occurred at.
+    ///
+    pub fn violations<'v, I>(&self, values: I) -> Vec<String>
+    where
+        I: IntoIterator<Item = (&'v str, &'v Value)>,
+    {
+        let mut violations = Vec::new();
+
+        for (source, value) in values {
+            for e in self.validator.iter_errors(value) {
+                let pointer = e.instance_path.to_string();
+                let location = if pointer.is_empty() {
+                    "the root"
+                } else {
+                    &pointer
+                };
+
+                violations.push(format!("{} at {}: {}", source, location, e));
+            }
+        }
+
+        violations
+    }
+
+    /// Validates values read from their sources, reporting every violation.
+    ///
+    pub fn validate<'v, I>(&self, values: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = (&'v str, &'v Value)>,
+    {
+        let violations = self.violations(values);
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Schema {
+                schemas: vec![self.path.clone()],
+                violations,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Schema;
+    use error::Error;
+    use tera::Value;
+
+    fn json(s: &str) -> Value {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_every_violation_is_reported() {
+        let schema = Schema::new(
+            "post.schema.json",
+            &json(
+                r#"{"type": "object", "required": ["title"],
+                    "properties": {"date": {"type": "string"}}}"#,
+            ),
+        )
+        .unwrap();
+        let valid = json(r#"{"title": "Hello", "date": "2018-01-01"}"#);
+        let invalid = json(r#"{"date": 20180101}"#);
+
+        assert!(schema.validate(vec![("a.yml", &valid)]).is_ok());
+
+        match schema.validate(vec![("a.yml", &valid), ("b.yml", &invalid)]) {
+            Err(Error::Schema {
+                schemas,
+                violations,
+            }) => {
+                assert_eq!(schemas, vec!["post.schema.json"]);
+                assert_eq!(
+                    violations,
+                    vec![
+                        "b.yml at /date: 20180101 is not of type \"string\"",
+                        "b.yml at the root: \"title\" is a required property",
+                    ]
+                );
+            }
+            r => panic!("expected schema violations, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_invalid_schema() {
+        assert!(Schema::new("bad.json", &json(r#"{"type": 7}"#)).is_err());
+    }
+}
@@ -0,0 +1,337 @@
+use error::Error;
+use tera::Value;
+
+/// A step in a selector.
+///
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    /// A key of a map, or an index of an array.
+    ///
+    Key(String),
+
+    /// Every item of an array, or every value of a map.
+    ///
+    Wildcard,
+}
+
+/// Selects a subtree of the data read by a plugin. Selectors are either a
+/// JSON Pointer (e.g. `/products/0/name`), whose segments are always taken
+/// literally, or a subset of JSONPath that supports keys, indices, and
+/// wildcards (e.g. `$.products[*].name` or `$['on sale'][0]`). Wildcards
+/// collect every match into an array.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+    text: String,
+    pointer: bool,
+    segments: Vec<Segment>,
+}
+
+impl Selector {
+    /// Splits a path with an optional selector after the last `#` (e.g.
+    /// `company.json#/products`). The text after the `#` is only a selector
+    /// if it starts with `/` or `$`, so other paths containing `#` are left
+    /// alone.
+    ///
+    pub fn split(arg_value: &str) -> Result<(&str, Option<Selector>), Error> {
+        match arg_value.rfind('#') {
+            Some(i) if arg_value[i + 1..].starts_with(['/', '$']) => {
+                Ok((&arg_value[..i], Some(Selector::parse(&arg_value[i + 1..])?)))
+            }
+            _ => Ok((arg_value, None)),
+        }
+    }
+
+    /// Parses a JSON Pointer, or a JSONPath starting with `$`.
+    ///
+    pub fn parse(s: &str) -> Result<Selector, Error> {
+        let segments = if s.starts_with('$') {
+            parse_path(s)?
+        } else {
+            parse_pointer(s)?
+        };
+
+        Ok(Selector {
+            text: s.to_string(),
+            pointer: !s.starts_with('$'),
+            segments,
+        })
+    }
+
+    /// Formats the first `n` segments of the selector, in the selector's own
+    /// syntax.
+    ///
+    fn prefix(&self, n: usize) -> String {
+        let mut prefix = if self.pointer {
+            String::new()
+        } else {
+            "$".to_string()
+        };
+
+        for segment in &self.segments[..n] {
+            match (self.pointer, segment) {
+                (true, Segment::Key(key)) => {
+                    prefix.push('/');
+                    prefix.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                }
+                (false, Segment::Key(key)) if array_index(key).is_some() => {
+                    prefix.push_str(&format!("[{}]", key))
+                }
+                (false, Segment::Key(key))
+                    if key.chars().all(|c| c.is_alphanumeric() || c == '_') =>
+                {
+                    prefix.push('.');
+                    prefix.push_str(key);
+                }
+                (false, Segment::Key(key)) => prefix.push_str(&format!("[{:?}]", key)),
+                (_, Segment::Wildcard) => prefix.push_str("[*]"),
+            }
+        }
+
+        if prefix.is_empty() {
+            "the root".to_string()
+        } else {
+            prefix
+        }
+    }
+
+    /// Selects the subtree of a value read from a source. If a segment
+    /// doesn't resolve, the error names it, along with the part of the
+    /// selector that did resolve.
+    ///
+    pub fn select(&self, source: &str, value: Value) -> Result<Value, Error> {
+        resolve(value, &self.segments, 0).map_err(|depth| {
+            let missing = match self.segments[depth] {
+                Segment::Key(ref key) => key.as_str(),
+                Segment::Wildcard => "*",
+            };
+
+            Error::Mapping(format!(
+                "Selector ({}) doesn't match the data in ({}): no ({}) in ({})",
+                self.text,
+                source,
+                missing,
+                self.prefix(depth)
+            ))
+        })
+    }
+}
+
+/// Resolves segments against a value, returning how many segments resolved
+/// if one of them doesn't.
+///
+fn resolve(value: Value, segments: &[Segment], depth: usize) -> Result<Value, usize> {
+    match segments.split_first() {
+        None => Ok(value),
+        Some((Segment::Key(key), rest)) => {
+            let child = match value {
+                Value::Object(mut map) => map.remove(key),
+                Value::Array(mut items) => match array_index(key) {
+                    Some(i) if i < items.len() => Some(items.swap_remove(i)),
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            match child {
+                Some(child) => resolve(child, rest, depth + 1),
+                None => Err(depth),
+            }
+        }
+        Some((Segment::Wildcard, rest)) => {
+            let items: Vec<Value> = match value {
+                Value::Array(items) => items,
+                Value::Object(map) => map.into_iter().map(|(_, value)| value).collect(),
+                _ => return Err(depth),
+            };
+
+            items
+                .into_iter()
+                .map(|item| resolve(item, rest, depth + 1))
+                .collect::<Result<Vec<Value>, usize>>()
+                .map(Value::Array)
+        }
+    }
+}
+
+/// Parses an array index, which is either `0`, or digits without a leading
+/// zero (e.g. `01` and `+1` aren't indices).
+///
+fn array_index(key: &str) -> Option<usize> {
+    if key.is_empty() || !key.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    if key.len() > 1 && key.starts_with('0') {
+        return None;
+    }
+
+    key.parse().ok()
+}
+
+/// Parses a JSON Pointer (RFC 6901), where `~1` escapes `/` and `~0`
+/// escapes `~`. Every segment is a literal key or index, so `*` only
+/// matches a key named `*`.
+///
+fn parse_pointer(s: &str) -> Result<Vec<Segment>, Error> {
+    if s.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    if !s.starts_with('/') {
+        return Err(Error::Mapping(format!(
+            "Expected selector ({}) to start with / or $",
+            s
+        )));
+    }
+
+    Ok(s[1..]
+        .split('/')
+        .map(|segment| Segment::Key(segment.replace("~1", "/").replace("~0", "~")))
+        .collect())
+}
+
+/// Parses the supported subset of JSONPath: `$` followed by `.key`, `.*`,
+/// `[0]`, `[*]`, `['key']`, or `["key"]`.
+///
+fn parse_path(s: &str) -> Result<Vec<Segment>, Error> {
+    let invalid = |reason: &str| {
+        Error::Mapping(format!(
+            "Expected a JSONPath selector like $.items[*].name in ({}): {}",
+            s, reason
+        ))
+    };
+    let mut segments = Vec::new();
+    let mut rest = &s[1..];
+
+    while !rest.is_empty() {
+        if rest.starts_with('.') {
+            let end = rest[1..].find(['.', '[']).map_or(rest.len(), |end| end + 1);
+            let key = &rest[1..end];
+
+            segments.push(match key {
+                "" => return Err(invalid("empty key")),
+                "*" => Segment::Wildcard,
+                key => Segment::Key(key.to_string()),
+            });
+            rest = &rest[end..];
+        } else if rest.starts_with('[') {
+            let end = match rest.find(']') {
+                Some(end) => end,
+                None => return Err(invalid("unclosed bracket")),
+            };
+            let inner = &rest[1..end];
+            let quoted = inner.len() >= 2
+                && (inner.starts_with('\'') && inner.ends_with('\'')
+                    || inner.starts_with('"') && inner.ends_with('"'));
+
+            segments.push(if inner == "*" {
+                Segment::Wildcard
+            } else if quoted {
+                Segment::Key(inner[1..inner.len() - 1].to_string())
+            } else if array_index(inner).is_some() {
+                Segment::Key(inner.to_string())
+            } else {
+                return Err(invalid("brackets need an index, *, or a quoted key"));
+            });
+            rest = &rest[end + 1..];
+        } else {
+            return Err(invalid("expected . or ["));
+        }
+    }
+
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Selector;
+    use tera::Value;
+
+    fn data() -> Value {
+        r#"{"products": [{"name": "a", "tags": ["x"]}, {"name": "b", "tags": []}],
+            "a/b": {"on sale": true}, "*": "star"}"#
+            .parse()
+            .unwrap()
+    }
+
+    fn select(selector: &str) -> Result<Value, String> {
+        Selector::parse(selector)
+            .and_then(|selector| selector.select("company.json", data()))
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_split() {
+        let (path, selector) = Selector::split("company.json#/products").unwrap();
+
+        assert_eq!(path, "company.json");
+        assert!(selector.is_some());
+        assert_eq!(
+            Selector::split("notes#1.json").unwrap(),
+            ("notes#1.json", None)
+        );
+    }
+
+    #[test]
+    fn test_pointer() {
+        assert_eq!(select("/products/1/name"), Ok(Value::String("b".into())));
+        assert_eq!(select("/a~1b/on sale"), Ok(Value::Bool(true)));
+        assert_eq!(select(""), Ok(data()));
+    }
+
+    #[test]
+    fn test_pointer_segments_are_literal() {
+        assert_eq!(select("/*"), Ok(Value::String("star".into())));
+        assert!(select("/products/*/name").is_err());
+    }
+
+    #[test]
+    fn test_indices_without_leading_zeros() {
+        assert_eq!(
+            select("/products/01/name"),
+            Err(
+                "Selector (/products/01/name) doesn't match the data in (company.json): no (01) \
+                 in (/products)"
+                    .into()
+            )
+        );
+        assert!(select("/products/+1/name").is_err());
+        assert!(select("$.products[01].name").is_err());
+        assert_eq!(select("/products/0/name"), Ok(Value::String("a".into())));
+    }
+
+    #[test]
+    fn test_path() {
+        assert_eq!(
+            select("$.products[*].name"),
+            Ok(r#"["a", "b"]"#.parse().unwrap())
+        );
+        assert_eq!(select("$['a/b'][\"on sale\"]"), Ok(Value::Bool(true)));
+        assert_eq!(
+            select("$.products[0].tags[0]"),
+            Ok(Value::String("x".into()))
+        );
+    }
+
+    #[test]
+    fn test_missing_segment() {
+        assert_eq!(
+            select("/products/7/name"),
+            Err(
+                "Selector (/products/7/name) doesn't match the data in (company.json): no (7) \
+                 in (/products)"
+                    .into()
+            )
+        );
+        assert_eq!(
+            select("$.products[*].price"),
+            Err(
+                "Selector ($.products[*].price) doesn't match the data in (company.json): no \
+                 (price) in ($.products[*])"
+                    .into()
+            )
+        );
+        assert!(select("$.products[x]").is_err());
+    }
+}
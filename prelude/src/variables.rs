@@ -0,0 +1,340 @@
+use error::Error;
+use std::collections::BTreeMap;
+use tera::{Context, Map, Value};
+
+/// How a value is merged with a value already bound to the same variable.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Merge {
+    /// Maps are merged, and any other collision is an error.
+    ///
+    Error,
+
+    /// Maps are merged, and any other value replaces the earlier one.
+    ///
+    Replace,
+
+    /// Like `Replace`, but arrays are appended to earlier arrays.
+    ///
+    Append,
+}
+
+impl Default for Merge {
+    #[inline]
+    fn default() -> Merge {
+        Merge::Error
+    }
+}
+
+/// Inserts a single binding, recording where each value came from.
+///
+struct Binder<'a> {
+    merge: Merge,
+    source: &'a str,
+    origins: &'a mut BTreeMap<String, String>,
+}
+
+impl<'a> Binder<'a> {
+    /// Inserts a value into a map. If a map is already bound to the key, the
+    /// value is merged into it. `name` is the dotted name of the key, which
+    /// is returned if the value collides with one that is already bound.
+    ///
+    fn insert(
+        &mut self,
+        map: &mut Map<String, Value>,
+        name: &str,
+        key: &str,
+        value: Value,
+    ) -> Result<(), String> {
+        match (map.get_mut(key), value) {
+            (None, value) => self.replace(map, name, key, value),
+            (Some(&mut Value::Object(ref mut existing)), Value::Object(values)) => {
+                // An empty map no longer has a value of its own.
+                //
+                self.origins.remove(name);
+
+                for (key, value) in values {
+                    self.insert(existing, &format!("{}.{}", name, key), &key, value)?;
+                }
+            }
+            (Some(&mut Value::Array(ref mut existing)), Value::Array(values))
+                if self.merge == Merge::Append =>
+            {
+                let origin = self.origins.entry(name.to_string()).or_default();
+
+                if !origin.is_empty() {
+                    origin.push_str(", ");
+                }
+
+                origin.push_str(self.source);
+                existing.extend(values);
+            }
+            (Some(_), _) if self.merge == Merge::Error => return Err(name.to_string()),
+            (Some(_), value) => self.replace(map, name, key, value),
+        }
+
+        Ok(())
+    }
+
+    /// Binds a value to a key, replacing anything bound to it before.
+    ///
+    fn replace(&mut self, map: &mut Map<String, Value>, name: &str, key: &str, value: Value) {
+        let prefix = format!("{}.", name);
+
+        self.origins
+            .retain(|origin, _| origin != name && !origin.starts_with(&prefix));
+        self.record(name, &value);
+        map.insert(key.to_string(), value);
+    }
+
+    /// Records the source of every value in a (possibly nested) value.
+    ///
+    fn record(&mut self, name: &str, value: &Value) {
+        match *value {
+            Value::Object(ref values) if !values.is_empty() => {
+                for (key, value) in values {
+                    self.record(&format!("{}.{}", name, key), value);
+                }
+            }
+            _ => {
+                self.origins
+                    .insert(name.to_string(), self.source.to_string());
+            }
+        }
+    }
+}
+
+/// The variables bound by plugins. Dotted variable names (e.g. `site.nav`)
+/// bind values in nested maps, which are merged with the values bound by
+/// other mappings.
+///
+#[derive(Debug, Default)]
+pub struct Variables {
+    vars: Map<String, Value>,
+    origins: BTreeMap<String, String>,
+    merge: Merge,
+    collision_hint: Option<String>,
+}
+
+impl Variables {
+    #[inline]
+    pub fn new() -> Variables {
+        Variables::default()
+    }
+
+    /// Creates variables that are merged with a strategy when they're bound
+    /// more than once.
+    ///
+    #[inline]
+    pub fn with_merge(merge: Merge) -> Variables {
+        Variables {
+            merge,
+            ..Variables::default()
+        }
+    }
+
+    /// Adds a hint to the errors for values that collide (e.g. how to layer
+    /// them instead).
+    ///
+    #[inline]
+    pub fn with_collision_hint<H>(mut self, hint: H) -> Variables
+    where
+        H: Into<String>,
+    {
+        self.collision_hint = Some(hint.into());
+        self
+    }
+
+    /// Binds a value read from a source (e.g. a file path) to a variable,
+    /// given the segments of its name. Maps bound to the same name are
+    /// always merged, but other values bound to the same key are only
+    /// layered if the merge strategy allows it, with later bindings taking
+    /// precedence.
+    ///
+    pub fn bind<S>(&mut self, var_path: &[S], source: &str, value: Value) -> Result<(), Error>
+    where
+        S: AsRef<str>,
+    {
+        let var_name = var_path
+            .iter()
+            .map(AsRef::as_ref)
+            .collect::<Vec<&str>>()
+            .join(".");
+        let (root, nested) = match var_path.split_first() {
+            Some((root, nested)) => (root.as_ref(), nested),
+            None => return Err(Error::Mapping("Expected a variable name to bind to".into())),
+        };
+        let mut value = value;
+
+        for segment in nested.iter().rev() {
+            let mut map = Map::new();
+
+            map.insert(segment.as_ref().to_string(), value);
+            value = Value::Object(map);
+        }
+
+        let mut binder = Binder {
+            merge: self.merge,
+            source,
+            origins: &mut self.origins,
+        };
+
+        binder
+            .insert(&mut self.vars, root, root, value)
+            .map_err(|name| {
+                let prefix = format!("{}.", name);
+                let existing = self
+                    .origins
+                    .range(name.clone()..)
+                    .find(|&(origin, _)| *origin == name || origin.starts_with(&prefix))
+                    .map(|(_, source)| source.as_str())
+                    .unwrap_or("another mapping");
+
+                let mut message = format!(
+                    "Variable ({}) is bound by both ({}) and ({}) while binding ({})",
+                    name, existing, source, var_name
+                );
+
+                if let Some(ref hint) = self.collision_hint {
+                    message.push_str(&format!(" ({})", hint));
+                }
+
+                Error::Mapping(message)
+            })
+    }
+
+    /// Returns the value bound to a top level variable.
+    ///
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.vars.get(name)
+    }
+
+    /// Returns the source of every value, by its dotted name. Maps are
+    /// expanded into their keys, and arrays that were appended to list every
+    /// source.
+    ///
+    #[inline]
+    pub fn origins(&self) -> &BTreeMap<String, String> {
+        &self.origins
+    }
+
+    /// Adds every variable to a tera context.
+    ///
+    pub fn extend_context(&self, context: &mut Context) {
+        for (name, value) in &self.vars {
+            context.insert(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Merge, Variables};
+    use tera::Value;
+
+    fn json(s: &str) -> Value {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_nested_bindings_are_merged() {
+        let mut vars = Variables::new();
+
+        vars.bind(&["site"], "site.yml", json(r#"{"title": "Home"}"#))
+            .unwrap();
+        vars.bind(&["site", "nav"], "nav.yml", json(r#"["a", "b"]"#))
+            .unwrap();
+        vars.bind(&["site", "meta", "author"], "--string", json(r#""ferris""#))
+            .unwrap();
+
+        assert_eq!(
+            vars.get("site"),
+            Some(&json(
+                r#"{"title": "Home", "nav": ["a", "b"], "meta": {"author": "ferris"}}"#
+            ))
+        );
+    }
+
+    #[test]
+    fn test_leaf_collision() {
+        let mut vars = Variables::new();
+
+        vars.bind(&["site"], "site.yml", json(r#"{"nav": {"home": "/"}}"#))
+            .unwrap();
+
+        let e = vars
+            .bind(
+                &["site", "nav", "home"],
+                "--string",
+                json(r#""/index.html""#),
+            )
+            .unwrap_err();
+
+        assert_eq!(
+            e.to_string(),
+            "Variable (site.nav.home) is bound by both (site.yml) and (--string) while binding \
+             (site.nav.home)"
+        );
+        assert!(vars
+            .bind(&["site", "nav", "home", "url"], "--int", json("1"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_collision_hint() {
+        let mut vars = Variables::new().with_collision_hint("use --merge to layer them");
+        let e = vars
+            .bind(&[] as &[&str], "--string", json("1"))
+            .unwrap_err();
+
+        assert_eq!(e.to_string(), "Expected a variable name to bind to");
+
+        vars.bind(&["title"], "a.yml", json("1")).unwrap();
+
+        let e = vars.bind(&["title"], "b.yml", json("2")).unwrap_err();
+
+        assert!(e
+            .to_string()
+            .ends_with("(title) (use --merge to layer them)"));
+    }
+
+    #[test]
+    fn test_layered_bindings() {
+        let mut vars = Variables::with_merge(Merge::Replace);
+
+        vars.bind(
+            &["config"],
+            "defaults.yml",
+            json(r#"{"db": {"host": "localhost", "port": 5432}, "tags": ["a"]}"#),
+        )
+        .unwrap();
+        vars.bind(
+            &["config"],
+            "production.yml",
+            json(r#"{"db": {"host": "db.example.com"}, "tags": ["b"]}"#),
+        )
+        .unwrap();
+
+        assert_eq!(
+            vars.get("config"),
+            Some(&json(
+                r#"{"db": {"host": "db.example.com", "port": 5432}, "tags": ["b"]}"#
+            ))
+        );
+        assert_eq!(vars.origins()["config.db.host"], "production.yml");
+        assert_eq!(vars.origins()["config.db.port"], "defaults.yml");
+        assert_eq!(vars.origins()["config.tags"], "production.yml");
+    }
+
+    #[test]
+    fn test_appended_arrays() {
+        let mut vars = Variables::with_merge(Merge::Append);
+
+        vars.bind(&["tags"], "a.yml", json(r#"["a"]"#)).unwrap();
+        vars.bind(&["tags"], "b.yml", json(r#"["b"]"#)).unwrap();
+
+        assert_eq!(vars.get("tags"), Some(&json(r#"["a", "b"]"#)));
+        assert_eq!(vars.origins()["tags"], "a.yml, b.yml");
+    }
+}